@@ -3,9 +3,10 @@ extern crate build_const;
 
 use aoc2020::{
     day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
-    day14, day15, day16, day17, day18, day19, /*%IMPORT%*/
+    day14, day15, day16, day17, day18, day19, day20, day21, day22, day23, day24,
+    day25, /*%IMPORT%*/
 };
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 
 build_const!("aoc2020");
 
@@ -92,8 +93,57 @@ fn aoc2020_bench(c: &mut Criterion) {
     c.bench_function("day 19 part 2", |b| {
         b.iter(|| day19::part2(black_box(DAY19)))
     });
+    c.bench_function("day 20 part 1", |b| {
+        b.iter(|| day20::part1(black_box(DAY20)))
+    });
+    c.bench_function("day 20 part 2", |b| {
+        b.iter(|| day20::part2(black_box(DAY20)))
+    });
+    c.bench_function("day 21 part 1", |b| {
+        b.iter(|| day21::part1(black_box(DAY21)))
+    });
+    c.bench_function("day 21 part 2", |b| {
+        b.iter(|| day21::part2(black_box(DAY21)))
+    });
+    c.bench_function("day 22 part 1", |b| {
+        b.iter(|| day22::part1(black_box(DAY22)))
+    });
+    c.bench_function("day 22 part 2", |b| {
+        b.iter(|| day22::part2(black_box(DAY22)))
+    });
+    c.bench_function("day 23 part 1", |b| {
+        b.iter(|| day23::part1(black_box(DAY23)))
+    });
+    c.bench_function("day 23 part 2", |b| {
+        b.iter(|| day23::part2(black_box(DAY23)))
+    });
+    c.bench_function("day 24 part 1", |b| {
+        b.iter(|| day24::part1(black_box(DAY24)))
+    });
+    c.bench_function("day 24 part 2", |b| {
+        b.iter(|| day24::part2(black_box(DAY24)))
+    });
+    c.bench_function("day 25 part 1", |b| {
+        b.iter(|| day25::part1(black_box(DAY25)))
+    });
     /*%CALL%*/
 }
 
-criterion_group!(aoc2020, aoc2020_bench);
+fn day15_last_seen_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day 15 last seen");
+    group.sample_size(10);
+
+    for &turns in [2020, 30_000_000].iter() {
+        group.bench_with_input(BenchmarkId::new("dense", turns), &turns, |b, &turns| {
+            b.iter(|| day15::nth_spoken::<day15::Dense, _, _>(black_box(DAY15), turns))
+        });
+        group.bench_with_input(BenchmarkId::new("hybrid", turns), &turns, |b, &turns| {
+            b.iter(|| day15::nth_spoken::<day15::Hybrid, _, _>(black_box(DAY15), turns))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(aoc2020, aoc2020_bench, day15_last_seen_bench);
 criterion_main!(aoc2020);
@@ -1,100 +1,29 @@
-use super::processor::{Instruction, InstructionParseError, Processor};
+use super::processor::{self, Instruction, InstructionParseError, Processor, Repair};
 use std::num::ParseIntError;
-use std::str::FromStr;
-
-fn locate_next_swappable(instructions: &[Instruction], idx: Option<usize>) -> Option<usize> {
-    if let Some(idx) = idx {
-        for (i, ins) in instructions[idx..].iter().enumerate().skip(1) {
-            match ins {
-                Instruction::Jmp(_) | Instruction::Nop(_) => {
-                    return Some(idx + i);
-                }
-                _ => (),
-            }
-        }
-    }
-
-    None
-}
-
-fn swap_next(instructions: &mut [Instruction], idx: Option<usize>, old_idx: Option<usize>) {
-    if let Some(old_idx) = old_idx {
-        if let Some(ins) = instructions.get_mut(old_idx) {
-            if let Instruction::Jmp(n) = ins {
-                *ins = Instruction::Nop(*n);
-            } else if let Instruction::Nop(n) = ins {
-                *ins = Instruction::Jmp(*n);
-            }
-        }
-    }
-
-    if let Some(idx) = idx {
-        if let Some(ins) = instructions.get_mut(idx) {
-            if let Instruction::Jmp(n) = ins {
-                *ins = Instruction::Nop(*n);
-            } else if let Instruction::Nop(n) = ins {
-                *ins = Instruction::Jmp(*n);
-            }
-        }
-    }
-}
 
 pub fn part1<'a, I, S>(lines: I) -> Result<i32, InstructionParseError<ParseIntError>>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let instructions = lines
-        .into_iter()
-        .map(|l| Instruction::from_str(l.as_ref()))
-        .collect::<Result<Vec<_>, _>>()?;
+    let instructions = processor::parse_program(lines)?;
 
+    // Either the program loops, and the accumulator is the one right before the loop, or it halts
     let mut processor = Processor::new(instructions.as_slice());
-    let mut visited = vec![];
-    loop {
-        if visited.contains(&processor.ip) {
-            return Ok(processor.acc);
-        } else {
-            visited.push(processor.ip);
-            let _ = processor.execute();
-        }
-    }
+    processor.run();
+
+    Ok(processor.acc)
 }
 
-pub fn part2<'a, I, S>(lines: I) -> Result<i32, InstructionParseError<ParseIntError>>
+// The changed instruction and the accumulator after the repaired program halts
+pub fn part2<'a, I, S>(lines: I) -> Result<Option<Repair>, InstructionParseError<ParseIntError>>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut instructions = lines
-        .into_iter()
-        .map(|l| Instruction::from_str(l.as_ref()))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let mut swap_idx = Some(0);
-    let mut old_swap_idx = None;
+    let instructions = processor::parse_program(lines)?;
 
-    loop {
-        swap_next(&mut instructions, swap_idx, old_swap_idx);
-        let mut processor = Processor::new(instructions.as_slice());
-        let mut visited = vec![];
-
-        loop {
-            if visited.contains(&processor.ip) {
-                break;
-            } else {
-                visited.push(processor.ip);
-                match processor.execute() {
-                    Ok(Some(acc)) => return Ok(acc),
-                    Err(_) => break,
-                    _ => (),
-                }
-            }
-        }
-
-        old_swap_idx = swap_idx;
-        swap_idx = locate_next_swappable(&instructions, swap_idx);
-    }
+    Ok(processor::repair(&instructions, Instruction::swapped))
 }
 
 #[cfg(test)]
@@ -114,8 +43,8 @@ mod tests {
 
     #[test]
     fn part2_test() {
-        let result = part2(EXAMPLE).unwrap();
+        let result = part2(EXAMPLE).unwrap().unwrap();
 
-        assert_eq!(result, 8);
+        assert_eq!((result.ip, result.acc), (7, 8));
     }
 }
@@ -1,67 +1,117 @@
-use crate::util;
-use std::collections::BTreeMap;
+use std::collections::VecDeque;
 use std::error::Error;
+use std::fmt;
 
-pub fn part1<'a, I, S>(lines: I) -> Result<u32, Box<dyn Error + Send + Sync>>
+// The largest joltage difference an adapter accepts in the puzzle
+pub const MAX_GAP: u32 = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum AdapterError {
+    Format,
+    // Two neighboring joltages in the chain are further apart than the maximum gap
+    Gap(u32, u32),
+    Overflow,
+}
+
+impl fmt::Display for AdapterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "AdapterError::Format"),
+            Self::Gap(from, to) => write!(f, "AdapterError::Gap({}, {})", from, to),
+            Self::Overflow => write!(f, "AdapterError::Overflow"),
+        }
+    }
+}
+
+impl Error for AdapterError {}
+
+#[derive(Debug, PartialEq)]
+pub struct Chain {
+    // The number of times each joltage difference occurs when using every adapter, indexed by
+    // the difference
+    pub differences: Vec<u64>,
+    // The number of ways to connect the outlet to the device
+    pub arrangements: u64,
+}
+
+// Goes through the adapters from the outlet up to the device, which is rated `max_gap` higher
+// than the highest adapter. Every joltage can be reached in as many ways as the joltages at most
+// `max_gap` below it combined
+pub fn chain(adapters: &[u32], max_gap: u32) -> Result<Chain, AdapterError> {
+    let mut sorted = adapters.to_vec();
+    sorted.sort_unstable();
+
+    let device = sorted
+        .last()
+        .copied()
+        .unwrap_or(0)
+        .checked_add(max_gap)
+        .ok_or(AdapterError::Overflow)?;
+
+    let mut differences = vec![0u64; max_gap as usize + 1];
+    // The joltages within reach of the current one, and the ways to get to each of them
+    let mut reachable: VecDeque<(u32, u64)> = VecDeque::from(vec![(0, 1)]);
+    let mut previous = 0;
+
+    for joltage in sorted.into_iter().chain(Some(device)) {
+        let gap = joltage - previous;
+        if gap > max_gap {
+            return Err(AdapterError::Gap(previous, joltage));
+        }
+        differences[gap as usize] += 1;
+        previous = joltage;
+
+        while let Some(&(j, _)) = reachable.front() {
+            if joltage - j <= max_gap {
+                break;
+            }
+            reachable.pop_front();
+        }
+
+        let ways = reachable
+            .iter()
+            .try_fold(0u64, |sum, &(_, ways)| sum.checked_add(ways))
+            .ok_or(AdapterError::Overflow)?;
+        reachable.push_back((joltage, ways));
+    }
+
+    Ok(Chain {
+        differences,
+        arrangements: reachable.back().map_or(1, |&(_, ways)| ways),
+    })
+}
+
+fn parse_adapters<'a, I, S>(lines: I) -> Result<Vec<u32>, AdapterError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut ones = 0;
-    let mut threes = 1;
-    let mut nums: Vec<u32> = util::parse_many(lines)?;
-    nums.insert(0, 0);
-    nums.sort_unstable();
-    for (n1, n2) in nums.iter().zip(nums.iter().skip(1)) {
-        match n2 - n1 {
-            1 => ones += 1,
-            3 => threes += 1,
-            _ => (),
-        }
-    }
-
-    Ok(ones * threes)
+    lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.parse().map_err(|_| AdapterError::Format))
+        .collect()
 }
 
-pub fn part2<'a, I, S>(lines: I) -> Result<usize, Box<dyn Error + Send + Sync>>
+pub fn part1<'a, I, S>(lines: I) -> Result<u64, AdapterError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut routes: BTreeMap<u32, Vec<&u32>> = BTreeMap::new();
-    let mut stack: BTreeMap<u32, usize> = BTreeMap::new();
-    let mut nums: Vec<u32> = util::parse_many(lines)?;
-    stack.insert(*nums.iter().max().unwrap(), 1);
-    nums.insert(0, 0);
-    nums.sort_unstable();
-
-    for num in 1..nums.len() {
-        routes.insert(
-            nums[num],
-            nums[num.checked_sub(3).unwrap_or(0)..num]
-                .iter()
-                .filter(|&n| nums[num] - n <= 3)
-                .collect(),
-        );
-    }
+    let chain = chain(&parse_adapters(lines)?, MAX_GAP)?;
 
-    loop {
-        let highest_key = *stack.keys().max().unwrap();
-        let (num, multiplier) = stack.remove_entry(&highest_key).unwrap();
-        if num == 0 {
-            return Ok(multiplier);
-        }
+    chain.differences[1]
+        .checked_mul(chain.differences[3])
+        .ok_or(AdapterError::Overflow)
+}
 
-        for route in routes.get(&num).unwrap().iter() {
-            if stack.contains_key(&route) {
-                if let Some(existing_multiplier) = stack.get_mut(&route) {
-                    *existing_multiplier += multiplier;
-                }
-            } else {
-                stack.insert(**route, multiplier);
-            }
-        }
-    }
+pub fn part2<'a, I, S>(lines: I) -> Result<u64, AdapterError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    Ok(chain(&parse_adapters(lines)?, MAX_GAP)?.arrangements)
 }
 
 #[cfg(test)]
@@ -73,6 +123,8 @@ mod tests {
         "38", "39", "11", "1", "32", "25", "35", "8", "17", "7", "9", "4", "2", "34", "10", "3",
     ];
 
+    static SMALL_EXAMPLE: &[&str] = &["16", "10", "15", "5", "1", "11", "7", "19", "6", "12", "4"];
+
     #[test]
     fn part1_test() {
         let result = part1(EXAMPLE).unwrap();
@@ -86,4 +138,32 @@ mod tests {
 
         assert_eq!(result, 19208);
     }
+
+    #[test]
+    fn chain_test() {
+        let adapters = parse_adapters(SMALL_EXAMPLE).unwrap();
+
+        assert_eq!(
+            chain(&adapters, 3),
+            Ok(Chain {
+                differences: vec![0, 7, 0, 5],
+                arrangements: 8
+            })
+        );
+        assert_eq!(chain(&adapters, 2), Err(AdapterError::Gap(1, 4)));
+        assert_eq!(chain(&adapters, 5).unwrap().arrangements, 423);
+        assert_eq!(chain(&[], 3).unwrap().arrangements, 1);
+    }
+
+    #[test]
+    fn overflow_test() {
+        // The arrangements of 1..=n grow like the tribonacci numbers
+        let adapters = (1..=100).collect::<Vec<_>>();
+
+        assert_eq!(
+            chain(&adapters[..70], 3).unwrap().arrangements,
+            2073693258389777176
+        );
+        assert_eq!(chain(&adapters, 3), Err(AdapterError::Overflow));
+    }
 }
@@ -1,58 +1,170 @@
-fn van_eck<'a, I, S>(lines: I, goal: u32) -> u32
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut last_seen = vec![0; goal as usize];
-    let mut last: u32 = 0;
-    let mut t = 1;
-
-    for line in lines.into_iter() {
-        let line = line.as_ref();
-        let chars: Vec<_> = line.split(',').collect();
-        println!("{:?}", chars);
-        for n in chars.iter().take(chars.len() - 1) {
-            let n = n.parse::<u32>().unwrap();
-            last_seen[n as usize] = t;
-            t += 1;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum SpokenError {
+    Format,
+}
+
+impl fmt::Display for SpokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "SpokenError::Format"),
         }
-        last = chars[chars.len() - 1].parse::<u32>().unwrap();
     }
+}
 
-    println!("{:?}", last_seen);
-    println!("{}", last);
+impl Error for SpokenError {}
 
-    loop {
-        if t == goal {
-            return last;
+// Remembers the turn every number was last spoken on. Turns start at 1, so 0 means never
+pub trait LastSeen {
+    fn with_turns(turns: usize) -> Self;
+
+    // Stores the new turn of the number, and returns the previous one
+    fn replace(&mut self, number: u32, turn: u32) -> u32;
+}
+
+// A table with an entry for every number, which is enough for any number spoken before the
+// given turn, because a spoken number is never larger than the turn it was spoken on
+pub struct Dense {
+    turns: Vec<u32>,
+}
+
+impl LastSeen for Dense {
+    fn with_turns(turns: usize) -> Self {
+        Dense {
+            turns: vec![0; turns],
         }
+    }
 
-        let old_t = last_seen[last as usize];
-        last_seen[last as usize] = t;
-        if old_t == 0 {
-            last = 0;
-        } else {
-            last = t - old_t;
+    fn replace(&mut self, number: u32, turn: u32) -> u32 {
+        let number = number as usize;
+        // Only the starting numbers or going past the expected turns can get here
+        if number >= self.turns.len() {
+            self.turns.resize(number + 1, 0);
+        }
+
+        std::mem::replace(&mut self.turns[number], turn)
+    }
+}
+
+// Numbers below the limit are kept in a table, and larger ones in a map. Large numbers are rare,
+// since they are spoken only when a number comes back after a long time
+pub struct Hybrid {
+    small: Vec<u32>,
+    large: HashMap<u32, u32>,
+}
+
+impl Hybrid {
+    const LIMIT: usize = 1 << 16;
+}
+
+impl LastSeen for Hybrid {
+    fn with_turns(turns: usize) -> Self {
+        Hybrid {
+            small: vec![0; turns.min(Self::LIMIT)],
+            large: HashMap::new(),
         }
+    }
+
+    fn replace(&mut self, number: u32, turn: u32) -> u32 {
+        match self.small.get_mut(number as usize) {
+            Some(last) => std::mem::replace(last, turn),
+            None => self.large.insert(number, turn).unwrap_or(0),
+        }
+    }
+}
+
+// The numbers spoken in the memory game, starting with the starting numbers. The sequence never
+// ends, and each number only depends on the number spoken right before it
+pub struct Spoken<T> {
+    last_seen: T,
+    starting: Vec<u32>,
+    turn: u32,
+    last: u32,
+}
 
-        t += 1;
+impl<T: LastSeen> Spoken<T> {
+    // `turns` is only the expected length of the game, used to size the table of turns
+    pub fn new(starting: &[u32], turns: usize) -> Self {
+        Spoken {
+            last_seen: T::with_turns(turns),
+            starting: starting.to_vec(),
+            turn: 0,
+            last: 0,
+        }
     }
 }
 
-pub fn part1<'a, I, S>(lines: I) -> u32
+impl<T: LastSeen> Iterator for Spoken<T> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        // The previous number is only stored now, after looking up when it was spoken before
+        let previous = if self.turn == 0 {
+            0
+        } else {
+            self.last_seen.replace(self.last, self.turn)
+        };
+
+        let next = match self.starting.get(self.turn as usize) {
+            Some(&number) => number,
+            None if previous == 0 => 0,
+            None => self.turn - previous,
+        };
+
+        self.turn += 1;
+        self.last = next;
+
+        Some(next)
+    }
+}
+
+fn parse_starting<'a, I, S>(lines: I) -> Result<Vec<u32>, SpokenError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    van_eck(lines, 2020)
+    let line = lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .find(|l| !l.is_empty())
+        .ok_or(SpokenError::Format)?;
+
+    line.split(',')
+        .map(|n| n.parse().map_err(|_| SpokenError::Format))
+        .collect()
+}
+
+// The number spoken on the given turn, counting from 1
+pub fn nth_spoken<'a, T, I, S>(lines: I, turn: usize) -> Result<u32, SpokenError>
+where
+    T: LastSeen,
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let starting = parse_starting(lines)?;
+
+    Spoken::<T>::new(&starting, turn)
+        .nth(turn.checked_sub(1).ok_or(SpokenError::Format)?)
+        .ok_or(SpokenError::Format)
 }
 
-pub fn part2<'a, I, S>(lines: I) -> u32
+pub fn part1<'a, I, S>(lines: I) -> Result<u32, SpokenError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    van_eck(lines, 30000000)
+    nth_spoken::<Dense, _, _>(lines, 2020)
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<u32, SpokenError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    nth_spoken::<Dense, _, _>(lines, 30_000_000)
 }
 
 #[cfg(test)]
@@ -65,6 +177,41 @@ mod tests {
     fn part1_test() {
         let result = part1(EXAMPLE);
 
-        assert_eq!(result, 436);
+        assert_eq!(result, Ok(436));
+    }
+
+    #[test]
+    fn spoken_test() {
+        let dense = Spoken::<Dense>::new(&[0, 3, 6], 10);
+        assert_eq!(
+            dense.take(10).collect::<Vec<_>>(),
+            [0, 3, 6, 0, 3, 3, 1, 0, 4, 0]
+        );
+
+        // Tables smaller than the game and large starting numbers work too
+        let dense = Spoken::<Dense>::new(&[100, 100], 1).take(300);
+        let hybrid = Spoken::<Hybrid>::new(&[100, 100], 1).take(300);
+        assert!(dense.eq(hybrid));
+
+        for starting in [[1, 3, 2], [2, 1, 3], [3, 1, 2]].iter() {
+            let dense = Spoken::<Dense>::new(starting, 2020).take(100_000);
+            let hybrid = Spoken::<Hybrid>::new(starting, 2020).take(100_000);
+            assert!(dense.eq(hybrid));
+        }
+    }
+
+    #[test]
+    fn nth_spoken_test() {
+        assert_eq!(nth_spoken::<Hybrid, _, _>(&["1,3,2"], 2020), Ok(1));
+        assert_eq!(nth_spoken::<Dense, _, _>(&["3,1,2"], 2020), Ok(1836));
+        assert_eq!(nth_spoken::<Dense, _, _>(&["0,3,6"], 3), Ok(6));
+        assert_eq!(
+            nth_spoken::<Dense, _, _>(&["0,3,6"], 0),
+            Err(SpokenError::Format)
+        );
+        assert_eq!(
+            nth_spoken::<Dense, _, _>(&["0,x"], 5),
+            Err(SpokenError::Format)
+        );
     }
 }
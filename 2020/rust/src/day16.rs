@@ -1,186 +1,247 @@
-pub fn part1<'a, I, S>(lines: I) -> u16
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut iter = lines.into_iter().map(|l| l.as_ref());
-    let mut valid_ranges = vec![];
-    let mut parsed_ranges = Vec::with_capacity(40);
-    let mut sum = 0;
-    loop {
-        if let Some(line) = iter.next() {
-            if line == "" {
-                break;
-            }
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-            for split in line.rsplitn(4, ' ').step_by(2).take(2) {
-                let mut nums = split.split('-');
-                if let Some(Ok(bot)) = nums.next().map(|v| v.parse::<u16>()) {
-                    if let Some(Ok(top)) = nums.next().map(|v| v.parse::<u16>()) {
-                        parsed_ranges.push((bot, top));
-                    }
-                }
-            }
+#[derive(Debug, PartialEq)]
+pub enum TicketError {
+    Format,
+    // No assignment of fields to columns fits the nearby tickets
+    Unsatisfiable,
+    // More than one assignment of fields to columns fits the nearby tickets
+    Ambiguous,
+}
+
+impl fmt::Display for TicketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "TicketError::Format"),
+            Self::Unsatisfiable => write!(f, "TicketError::Unsatisfiable"),
+            Self::Ambiguous => write!(f, "TicketError::Ambiguous"),
         }
     }
+}
+
+impl Error for TicketError {}
+
+// A ticket field and the inclusive ranges of values it accepts, like "class: 1-3 or 5-7"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub ranges: Vec<(u32, u32)>,
+}
 
-    while let Some((mut new_bot, mut new_top)) = parsed_ranges.pop() {
-        valid_ranges = valid_ranges
+impl Rule {
+    pub fn contains(&self, value: u32) -> bool {
+        self.ranges
             .iter()
-            .filter_map(|&(b, t)| {
-                if t < new_bot - 1 || b > new_top + 1 {
-                    Some((b, t))
-                } else {
-                    new_bot = new_bot.min(b);
-                    new_top = new_top.max(t);
-                    None
-                }
-            })
-            .collect();
-        valid_ranges.push((new_bot, new_top));
+            .any(|&(low, high)| low <= value && value <= high)
     }
+}
 
-    println!("{:?}", valid_ranges);
+impl FromStr for Rule {
+    type Err = TicketError;
 
-    let mut iter = iter.skip(4);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, ranges) = s.split_once(": ").ok_or(TicketError::Format)?;
+        let ranges = ranges
+            .split(" or ")
+            .map(|range| {
+                let (low, high) = range.split_once('-').ok_or(TicketError::Format)?;
+                let low = low.parse().map_err(|_| TicketError::Format)?;
+                let high = high.parse().map_err(|_| TicketError::Format)?;
+                Ok((low, high))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-    while let Some(line) = iter.next() {
-        for num in line.split(',').filter_map(|n| n.parse::<u16>().ok()) {
-            if !valid_ranges
-                .iter()
-                .any(|&(bot, top)| num >= bot && num <= top)
-            {
-                sum += num;
-                break;
-            }
-        }
+        Ok(Rule {
+            name: name.to_string(),
+            ranges,
+        })
     }
+}
 
-    sum
+fn parse_ticket(line: &str) -> Result<Vec<u32>, TicketError> {
+    line.split(',')
+        .map(|v| v.parse().map_err(|_| TicketError::Format))
+        .collect()
 }
 
-pub fn part2<'a, I, S>(lines: I) -> Option<u64>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut iter = lines.into_iter().map(|l| l.as_ref());
-    let mut columns: Vec<Vec<u16>> = vec![vec![]; 20];
-    let mut ranges = Vec::with_capacity(20);
-    let mut valid_ranges = vec![];
-    let mut combinations = vec![vec![]; 20];
-
-    // Collect ranges from first 1/3 of input
-    loop {
-        if let Some(line) = iter.next() {
-            if line == "" {
-                break;
-            }
+struct Notes {
+    rules: Vec<Rule>,
+    ticket: Vec<u32>,
+    nearby: Vec<Vec<u32>>,
+}
+
+impl Notes {
+    fn new<'a, I, S>(lines: I) -> Result<Self, TicketError>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        let mut iter = lines.into_iter().map(|l| l.as_ref());
+
+        let rules = iter
+            .by_ref()
+            .take_while(|l| !l.is_empty())
+            .map(Rule::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
 
-            let mut rs = line
-                .rsplitn(4, ' ')
-                .step_by(2)
-                .take(2)
-                .map(|s| s.split('-').filter_map(|v| v.parse::<u16>().ok()));
-            if let Some(mut i) = rs.next() {
-                let n1 = i.next()?;
-                let n2 = i.next()?;
-
-                if let Some(mut i) = rs.next() {
-                    let n3 = i.next()?;
-                    let n4 = i.next()?;
-                    ranges.push((n3, n4, n1, n2));
-                }
-            };
+        if iter.next() != Some("your ticket:") {
+            return Err(TicketError::Format);
         }
-    }
+        let ticket = parse_ticket(iter.next().ok_or(TicketError::Format)?)?;
+
+        if iter.find(|l| !l.is_empty()) != Some("nearby tickets:") {
+            return Err(TicketError::Format);
+        }
+        let nearby = iter
+            .filter(|l| !l.is_empty())
+            .map(parse_ticket)
+            .collect::<Result<Vec<_>, _>>()?;
 
-    // Determine the range of valid numbers
-    for &(bot1, top1, bot2, top2) in ranges.iter() {
-        for &(mut bot, mut top) in [(bot1, top1), (bot2, top2)].iter() {
-            valid_ranges = valid_ranges
+        // Every rule gets a bit, and every ticket has a column for each rule
+        if rules.len() > 32
+            || nearby
                 .iter()
-                .filter_map(|&(b, t)| {
-                    if t < bot - 1 || b > top + 1 {
-                        Some((b, t))
-                    } else {
-                        bot = bot.min(b);
-                        top = top.max(t);
-                        None
-                    }
-                })
-                .collect();
-            valid_ranges.push((bot, top));
+                .chain([&ticket])
+                .any(|t| t.len() != rules.len())
+        {
+            return Err(TicketError::Format);
         }
+
+        Ok(Notes {
+            rules,
+            ticket,
+            nearby,
+        })
     }
 
-    // Collect second 1/3 of input that contains my ticket
-    let mut iter = iter.skip(1);
-    let my_nums = iter
-        .next()?
-        .split(',')
-        .filter_map(|v| v.parse::<u16>().ok())
-        .collect::<Vec<_>>();
-
-    // Collect last 1/3 of input and group them into columns
-    let mut iter = iter.skip(2);
-    while let Some(line) = iter.next() {
-        let nums = line
-            .split(',')
-            .filter_map(|v| v.parse::<u16>().ok())
-            .collect::<Vec<_>>();
-        // Discard tickets that contain invalid numbers
-        if nums
+    // The bitmask of rules accepting the value
+    fn mask(&self, value: u32) -> u32 {
+        self.rules
             .iter()
-            .all(|&n| valid_ranges.iter().any(|&(bot, top)| bot <= n && n <= top))
-        {
-            let mut i = 0;
-            for num in nums.iter() {
-                columns[i].push(*num);
-                i += 1;
+            .enumerate()
+            .filter(|(_, r)| r.contains(value))
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    fn invalid_values(&self) -> impl Iterator<Item = u32> + '_ {
+        self.nearby
+            .iter()
+            .flatten()
+            .copied()
+            .filter(move |&v| self.mask(v) == 0)
+    }
+
+    // For every column, the bitmask of rules accepting all of its values on the valid tickets
+    fn candidates(&self) -> Vec<u32> {
+        let all = (1u64 << self.rules.len()) - 1;
+        let mut candidates = vec![all as u32; self.rules.len()];
+
+        for ticket in &self.nearby {
+            let masks = ticket.iter().map(|&v| self.mask(v)).collect::<Vec<_>>();
+            if masks.contains(&0) {
+                continue;
+            }
+
+            for (candidate, mask) in candidates.iter_mut().zip(masks) {
+                *candidate &= mask;
             }
         }
+
+        candidates
     }
+}
 
-    // Determine possible column - range group combinations
-    for (i_col, col) in columns.iter().enumerate() {
-        for (i_range, (bot1, top1, bot2, top2)) in ranges.iter().enumerate() {
-            if col
-                .iter()
-                .all(|n| (bot1 <= n && n <= top1) || (bot2 <= n && n <= top2))
-            {
-                combinations[i_col].push(i_range);
+// Counts the assignments of the remaining columns to distinct rules, stopping at two, and keeps
+// the first one found in `found`
+fn search(
+    candidates: &[u32],
+    used: u32,
+    assignment: &mut [Option<usize>],
+    found: &mut Vec<usize>,
+) -> usize {
+    // Continue with the open column with the fewest options
+    let column = (0..candidates.len())
+        .filter(|&c| assignment[c].is_none())
+        .min_by_key(|&c| (candidates[c] & !used).count_ones());
+
+    let column = match column {
+        Some(column) => column,
+        None => {
+            if found.is_empty() {
+                found.extend(assignment.iter().flatten());
             }
+            return 1;
+        }
+    };
+
+    let mut count = 0;
+    let mut options = candidates[column] & !used;
+    while options != 0 && count < 2 {
+        let rule = options.trailing_zeros() as usize;
+        options &= options - 1;
+
+        assignment[column] = Some(rule);
+        count += search(candidates, used | 1 << rule, assignment, found);
+    }
+    assignment[column] = None;
+
+    count
+}
+
+// Finds the rule of every column. Columns with a single option are assigned first, which takes
+// that rule away from the others, and anything left after that is searched
+fn assign(mut candidates: Vec<u32>) -> Result<Vec<usize>, TicketError> {
+    let mut assignment = vec![None; candidates.len()];
+    let mut used = 0;
+
+    while let Some(column) =
+        (0..candidates.len()).find(|&c| assignment[c].is_none() && candidates[c].count_ones() == 1)
+    {
+        let bit = candidates[column];
+        assignment[column] = Some(bit.trailing_zeros() as usize);
+        used |= bit;
+
+        for candidate in candidates.iter_mut() {
+            *candidate &= !bit;
         }
     }
 
-    let mut combinations = combinations
-        .into_iter()
-        .enumerate()
-        .map(|(i, p)| (i, p))
-        .collect::<Vec<_>>();
-    combinations.sort_unstable_by(|a, b| a.1.len().cmp(&b.1.len()));
-
-    let mut pairings: Vec<(usize, usize)> = vec![];
-    for comb in combinations.into_iter() {
-        let new_pairing = (
-            comb.0,
-            *comb
-                .1
-                .iter()
-                .filter(|&&c| pairings.iter().all(|&p| p.1 != c))
-                .next()?,
-        );
-        pairings.push(new_pairing);
+    if assignment.iter().any(|a| a.is_none()) {
+        let mut found = vec![];
+        return match search(&candidates, used, &mut assignment, &mut found) {
+            0 => Err(TicketError::Unsatisfiable),
+            1 => Ok(found),
+            _ => Err(TicketError::Ambiguous),
+        };
     }
 
-    Some(
-        pairings
-            .into_iter()
-            .filter_map(|(c, r)| if r < 6 { Some(c) } else { None })
-            .map(|i| my_nums[i] as u64)
-            .product(),
-    )
+    Ok(assignment.into_iter().flatten().collect())
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<u32, TicketError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    Ok(Notes::new(lines)?.invalid_values().sum())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<u64, TicketError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let notes = Notes::new(lines)?;
+    let assignment = assign(notes.candidates())?;
+
+    Ok(assignment
+        .iter()
+        .zip(&notes.ticket)
+        .filter(|&(&rule, _)| notes.rules[rule].name.starts_with("departure"))
+        .map(|(_, &value)| value as u64)
+        .product())
 }
 
 #[cfg(test)]
@@ -202,10 +263,78 @@ mod tests {
         "38,6,12",
     ];
 
+    static EXAMPLE2: &[&str] = &[
+        "class: 0-1 or 4-19",
+        "row: 0-5 or 8-19",
+        "seat: 0-13 or 16-19",
+        "",
+        "your ticket:",
+        "11,12,13",
+        "",
+        "nearby tickets:",
+        "3,9,18",
+        "15,1,5",
+        "5,14,9",
+    ];
+
     #[test]
     fn part1_test() {
         let result = part1(EXAMPLE);
 
-        assert_eq!(result, 71);
+        assert_eq!(result, Ok(71));
+    }
+
+    #[test]
+    fn assign_test() {
+        let notes = Notes::new(EXAMPLE2).unwrap();
+        let names = assign(notes.candidates())
+            .unwrap()
+            .into_iter()
+            .map(|r| notes.rules[r].name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["row", "class", "seat"]);
+    }
+
+    #[test]
+    fn search_test() {
+        assert_eq!(
+            assign(vec![0b0011, 0b0110, 0b1100, 0b1001]),
+            Err(TicketError::Ambiguous)
+        );
+        assert_eq!(
+            assign(vec![0b1100, 0b0011, 0b0011, 0b0011]),
+            Err(TicketError::Unsatisfiable)
+        );
+        assert_eq!(assign(vec![0b011, 0b110, 0b100]), Ok(vec![0, 1, 2]));
+        assert_eq!(assign(vec![0b01, 0b01]), Err(TicketError::Unsatisfiable));
+    }
+
+    #[test]
+    fn large_values_test() {
+        let notes = [
+            "huge: 0-4000000000 or 4294967295-4294967295",
+            "",
+            "your ticket:",
+            "4294967295",
+            "",
+            "nearby tickets:",
+            "4000000001",
+            "3999999999",
+        ];
+
+        assert_eq!(part1(&notes), Ok(4000000001));
+        assert_eq!(part2(&notes), Ok(1));
+    }
+
+    #[test]
+    fn rule_test() {
+        let rule = "departure location: 25-80 or 90-961"
+            .parse::<Rule>()
+            .unwrap();
+
+        assert_eq!(rule.name, "departure location");
+        assert!(rule.contains(25) && rule.contains(961) && !rule.contains(85));
+        assert_eq!("class 1-3".parse::<Rule>(), Err(TicketError::Format));
     }
 }
@@ -1,232 +1,191 @@
-use std::convert::TryInto;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 
-enum Part {
-    One,
-    Two,
-}
-
 #[derive(Debug)]
-enum ParseUniverseError {
+pub enum ParseUniverseError {
     Value,
+    Dimension,
 }
 
 impl fmt::Display for ParseUniverseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Self::Value => write!(f, "ParseUniverseError::Value"),
+            Self::Dimension => write!(f, "ParseUniverseError::Dimension"),
         }
     }
 }
 
 impl Error for ParseUniverseError {}
 
-struct Universe {
-    planes: Vec<Vec<Vec<Cell>>>,
-    x: usize,
-    y: usize,
+// The active cubes of an N-dimensional pocket dimension, where the starting slice lies on the
+// first two axes.
+//
+// Every other axis starts out at 0, so the state stays symmetric under mirroring any of these
+// axes and under swapping any two of them. Only one cube of every such group is stored: the one
+// with its extra coordinates non-negative and sorted
+pub struct Universe<const N: usize> {
+    active: HashSet<[i32; N]>,
+    offsets: Vec<[i32; N]>,
 }
 
-impl Universe {
-    fn new<'a, I, S>(lines: I, cycles: usize, part: Part) -> Result<Self, ParseUniverseError>
+impl<const N: usize> Universe<N> {
+    pub fn new<'a, I, S>(lines: I) -> Result<Self, ParseUniverseError>
     where
         I: IntoIterator<Item = &'a S>,
         S: AsRef<str> + 'a,
     {
-        let mut cells = vec![];
+        if N < 2 {
+            return Err(ParseUniverseError::Dimension);
+        }
+
+        let mut active = HashSet::new();
 
-        let (mut x, mut y) = (0, 0);
-        for line in lines.into_iter().map(|l| l.as_ref()) {
-            for c in line.chars() {
+        for (y, line) in lines
+            .into_iter()
+            .map(|l| l.as_ref())
+            .filter(|l| !l.is_empty())
+            .enumerate()
+        {
+            for (x, c) in line.chars().enumerate() {
                 match c {
-                    '.' => cells.push(Cell::Inactive),
-                    '#' => cells.push(Cell::Active),
+                    '.' => {}
+                    '#' => {
+                        let mut cube = [0; N];
+                        cube[0] = x as i32;
+                        cube[1] = y as i32;
+                        active.insert(cube);
+                    }
                     _ => return Err(ParseUniverseError::Value),
                 }
-                x += 1;
             }
-            y += 1;
         }
 
-        // Assuming lines are of equal width
-        x = x / y;
-        let new_x = x + 2 * cycles;
-        let new_y = y + 2 * cycles;
-        let z = 1 + 2 * cycles;
-        let w = match part {
-            Part::One => 1,
-            Part::Two => 1 + 2 * cycles,
-        };
-
-        let empty_plane = vec![Cell::Inactive; new_x * new_y];
-        let mut planes = vec![vec![empty_plane.clone(); z]; w];
-
-        let mut starting_plane = empty_plane.clone();
-        cells
-            .into_iter()
-            .enumerate()
-            .filter_map(|(i, c)| match c {
-                Cell::Active => Some((i, c)),
-                _ => None,
-            })
-            .for_each(|(i, c)| {
-                let idx = new_x * ((new_y - y) / 2 + i / x) + (new_x - x) / 2 + i % x;
-                starting_plane[idx] = c;
-            });
-
-        planes[(w - 1) / 2][cycles] = starting_plane;
-
         Ok(Universe {
-            planes,
-            x: new_x,
-            y: new_y,
+            active,
+            offsets: offsets(),
         })
     }
 
-    pub fn get_cell_count(&self, cell_type: Cell) -> usize {
-        self.planes
-            .iter()
-            .map(|s| {
-                s.iter()
-                    .map(|p| p.iter().filter(|&c| *c == cell_type).count())
-                    .sum::<usize>()
-            })
-            .sum()
-    }
-
     pub fn tick(&mut self) {
-        let mut new_planes = self.planes.clone();
-
-        for w in 0..self.planes.len() {
-            for z in 0..self.planes[w].len() {
-                for i in 0..self.x * self.y {
-                    let neighbors = self.get_neighbors(w, z, i);
-                    let cell = self.get_cell(w, z, i);
-
-                    match *cell {
-                        Cell::Inactive => {
-                            if neighbors.iter().filter(|&&n| *n == Cell::Active).count() == 3 {
-                                new_planes[w][z][i] = Cell::Active;
-                            }
-                        }
-                        Cell::Active => {
-                            let count = neighbors.iter().filter(|&&n| *n == Cell::Active).count();
-                            if count != 2 && count != 3 {
-                                new_planes[w][z][i] = Cell::Inactive;
-                            }
-                        }
-                    }
+        // Every stored cube stands for `size` cubes, and so does every neighbor it counts for.
+        // The counts of a stored cube add up to its real neighbor count times its own group size
+        let mut counts: HashMap<[i32; N], usize> = HashMap::new();
+        for cube in self.active.iter() {
+            let size = group_size(cube);
+
+            for offset in self.offsets.iter() {
+                let mut neighbor = *cube;
+                for (n, o) in neighbor.iter_mut().zip(offset) {
+                    *n += o;
                 }
+                *counts.entry(canonical(neighbor)).or_default() += size;
             }
         }
 
-        self.planes = new_planes;
+        let active = &self.active;
+        self.active = counts
+            .into_iter()
+            .filter(|(cube, count)| match count / group_size(cube) {
+                3 => true,
+                2 => active.contains(cube),
+                _ => false,
+            })
+            .map(|(cube, _)| cube)
+            .collect();
     }
 
-    fn get_cell(&self, w: usize, z: usize, i: usize) -> &Cell {
-        &self.planes[w][z][i]
+    pub fn active_count(&self) -> usize {
+        self.active.iter().map(group_size).sum()
     }
+}
 
-    fn get_neighbors(&self, w: usize, z: usize, i: usize) -> Vec<&Cell> {
-        let z: i32 = z.try_into().unwrap(); // Safe because z is small
-        let w: i32 = w.try_into().unwrap(); // Safe because w is small
-        let mut neighbors = vec![];
-        for w_offset in [-1, 0, 1].iter() {
-            let w_idx = w + w_offset;
-            if w_idx < 0 || self.planes.len() <= w_idx as usize {
-                continue;
-            }
-
-            for z_offset in [-1, 0, 1].iter() {
-                let z_idx = z + z_offset;
-                if z_idx < 0 || self.planes[w_idx as usize].len() <= z_idx as usize {
-                    continue;
-                }
-
-                if let Some(space) = self.planes.get(w_idx as usize) {
-                    if let Some(plane) = space.get(z_idx as usize) {
-                        let left = i % self.x != 0;
-                        let right = i % self.x != self.x - 1;
-                        let up = i >= self.x;
-                        let down = i <= (self.y - 1) * self.x - 1;
-
-                        if left {
-                            neighbors.push(&plane[i - 1]);
-                        }
-
-                        if right {
-                            neighbors.push(&plane[i + 1]);
-                        }
-
-                        if up {
-                            neighbors.push(&plane[i - self.x]);
-                        }
-
-                        if down {
-                            neighbors.push(&plane[i + self.x]);
-                        }
-
-                        if left && up {
-                            neighbors.push(&plane[i - self.x - 1]);
-                        }
-
-                        if right && up {
-                            neighbors.push(&plane[i - self.x + 1]);
-                        }
+// All offsets to the 3^N - 1 neighbors of a cube
+fn offsets<const N: usize>() -> Vec<[i32; N]> {
+    let mut offsets = vec![[0; N]];
 
-                        if left && down {
-                            neighbors.push(&plane[i + self.x - 1]);
-                        }
+    for axis in 0..N {
+        offsets = offsets
+            .into_iter()
+            .flat_map(|offset| {
+                [-1, 0, 1].iter().map(move |&d| {
+                    let mut offset = offset;
+                    offset[axis] = d;
+                    offset
+                })
+            })
+            .collect();
+    }
 
-                        if right && down {
-                            neighbors.push(&plane[i + self.x + 1]);
-                        }
+    offsets.retain(|o| o.iter().any(|&d| d != 0));
+    offsets
+}
 
-                        if *z_offset != 0 || *w_offset != 0 {
-                            neighbors.push(&plane[i]);
-                        }
-                    }
-                }
-            }
-        }
+// The cube representing all mirrored and swapped versions of the given cube
+fn canonical<const N: usize>(mut cube: [i32; N]) -> [i32; N] {
+    let extra = &mut cube[2..];
+    extra.iter_mut().for_each(|c| *c = c.abs());
+    extra.sort_unstable();
 
-        neighbors
-    }
+    cube
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Cell {
-    Active,
-    Inactive,
+// The number of distinct cubes a canonical cube represents
+fn group_size<const N: usize>(cube: &[i32; N]) -> usize {
+    let extra = &cube[2..];
+
+    // Every non-zero coordinate can be mirrored
+    let mirrored = 1 << extra.iter().filter(|&&c| c != 0).count();
+
+    // The coordinates can be arranged in k! / (m1! * m2! * ...) ways, where the m are the number
+    // of times each value repeats
+    let factorial = |n: usize| (1..=n).product::<usize>();
+    let repeats = extra
+        .iter()
+        .fold(HashMap::new(), |mut repeats, c| {
+            *repeats.entry(c).or_insert(0) += 1;
+            repeats
+        })
+        .values()
+        .map(|&m| factorial(m))
+        .product::<usize>();
+
+    mirrored * factorial(extra.len()) / repeats
 }
 
-pub fn part1<'a, I, S>(lines: I) -> Option<usize>
+// Runs the given number of cycles in N dimensions and counts the active cubes
+pub fn simulate<'a, const N: usize, I, S>(
+    lines: I,
+    cycles: usize,
+) -> Result<usize, ParseUniverseError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut u = Universe::new(lines, 6, Part::One).ok()?;
+    let mut universe = Universe::<N>::new(lines)?;
 
-    for _ in 0..6 {
-        u.tick();
+    for _ in 0..cycles {
+        universe.tick();
     }
 
-    Some(u.get_cell_count(Cell::Active))
+    Ok(universe.active_count())
 }
 
-pub fn part2<'a, I, S>(lines: I) -> Option<usize>
+pub fn part1<'a, I, S>(lines: I) -> Result<usize, ParseUniverseError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut u = Universe::new(lines, 6, Part::Two).ok()?;
-
-    for _ in 0..6 {
-        u.tick();
-    }
+    simulate::<3, _, _>(lines, 6)
+}
 
-    Some(u.get_cell_count(Cell::Active))
+pub fn part2<'a, I, S>(lines: I) -> Result<usize, ParseUniverseError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    simulate::<4, _, _>(lines, 6)
 }
 
 #[cfg(test)]
@@ -248,4 +207,59 @@ mod tests {
 
         assert_eq!(result, 848);
     }
+
+    // Simulates without folding any axes
+    fn unfolded<const N: usize>(cycles: usize) -> usize {
+        let universe = Universe::<N>::new(EXAMPLE).unwrap();
+        let mut active = universe.active;
+
+        for _ in 0..cycles {
+            let mut counts: HashMap<[i32; N], usize> = HashMap::new();
+            for cube in active.iter() {
+                for offset in universe.offsets.iter() {
+                    let mut neighbor = *cube;
+                    for (n, o) in neighbor.iter_mut().zip(offset) {
+                        *n += o;
+                    }
+                    *counts.entry(neighbor).or_default() += 1;
+                }
+            }
+
+            active = counts
+                .into_iter()
+                .filter(|&(cube, count)| count == 3 || count == 2 && active.contains(&cube))
+                .map(|(cube, _)| cube)
+                .collect();
+        }
+
+        active.len()
+    }
+
+    #[test]
+    fn folding_test() {
+        for cycles in 0..4 {
+            assert_eq!(
+                simulate::<2, _, _>(EXAMPLE, cycles).unwrap(),
+                unfolded::<2>(cycles)
+            );
+            assert_eq!(
+                simulate::<3, _, _>(EXAMPLE, cycles).unwrap(),
+                unfolded::<3>(cycles)
+            );
+            assert_eq!(
+                simulate::<5, _, _>(EXAMPLE, cycles).unwrap(),
+                unfolded::<5>(cycles)
+            );
+        }
+    }
+
+    #[test]
+    fn group_size_test() {
+        assert_eq!(group_size(&[5, 5]), 1);
+        assert_eq!(group_size(&[0, 0, 0, 0]), 1);
+        assert_eq!(group_size(&[0, 0, 0, 1]), 4);
+        assert_eq!(group_size(&[0, 0, 1, 1]), 4);
+        assert_eq!(group_size(&[0, 0, 0, 1, 2]), 2 * 2 * 6);
+        assert_eq!(offsets::<4>().len(), 80);
+    }
 }
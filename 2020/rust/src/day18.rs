@@ -1,136 +1,341 @@
-fn eval<'a, S>(s: S) -> Option<u64>
-where
-    S: AsRef<str> + 'a,
-{
-    let line = s.as_ref();
-    let mut level = 0;
-    let mut parens_expr = String::new();
-    let mut acc = 0;
-    let mut op = '+';
-    for c in line.chars() {
-        match c {
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedChar(char),
+    UnexpectedToken,
+    UnexpectedEnd,
+    UnknownOperator,
+    UnclosedParen,
+    Overflow,
+}
+
+// An error in an expression, with the 1-based column it happened at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpressionError {
+    pub kind: ErrorKind,
+    pub column: usize,
+}
+
+impl ExpressionError {
+    fn new(kind: ErrorKind, column: usize) -> Self {
+        ExpressionError { kind, column }
+    }
+
+    // The expression with a marker under the column of the error
+    pub fn annotate(&self, line: &str) -> String {
+        format!("{}\n{:>w$}", line, "^", w = self.column)
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::UnexpectedChar(c) => write!(f, "ExpressionError::UnexpectedChar({})", c),
+            ErrorKind::UnexpectedToken => write!(f, "ExpressionError::UnexpectedToken"),
+            ErrorKind::UnexpectedEnd => write!(f, "ExpressionError::UnexpectedEnd"),
+            ErrorKind::UnknownOperator => write!(f, "ExpressionError::UnknownOperator"),
+            ErrorKind::UnclosedParen => write!(f, "ExpressionError::UnclosedParen"),
+            ErrorKind::Overflow => write!(f, "ExpressionError::Overflow"),
+        }?;
+        write!(f, " at column {}", self.column)
+    }
+}
+
+impl Error for ExpressionError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Mul,
+}
+
+impl Operator {
+    fn apply(self, lhs: u64, rhs: u64) -> Option<u64> {
+        match self {
+            Operator::Add => lhs.checked_add(rhs),
+            Operator::Mul => lhs.checked_mul(rhs),
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operator::Add => write!(f, "+"),
+            Operator::Mul => write!(f, "*"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Number(u64),
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().zip(1..).peekable();
+
+    while let Some((c, column)) = chars.next() {
+        let kind = match c {
             ' ' => continue,
-            '+' | '*' => {
-                if level == 0 {
-                    op = c;
-                } else {
-                    parens_expr.push(c);
-                }
-            }
-            '(' => {
-                if level > 0 {
-                    parens_expr.push(c);
-                }
-                level += 1;
-            }
-            ')' => {
-                level -= 1;
-                if level > 0 {
-                    parens_expr.push(c);
-                } else {
-                    if let Some(num) = eval(&parens_expr) {
-                        match op {
-                            '+' => acc += num,
-                            '*' => acc *= num,
-                            _ => (),
-                        }
-                    }
-                    parens_expr.clear();
-                }
-            }
+            '+' => TokenKind::Operator(Operator::Add),
+            '*' => TokenKind::Operator(Operator::Mul),
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
             _ => {
-                if level == 0 {
-                    let num = c.to_digit(10)? as u64;
-                    match op {
-                        '+' => acc += num,
-                        '*' => acc *= num,
-                        _ => (),
-                    }
-                } else {
-                    parens_expr.push(c);
+                let mut number = c
+                    .to_digit(10)
+                    .ok_or(ExpressionError::new(ErrorKind::UnexpectedChar(c), column))?
+                    as u64;
+                while let Some(digit) = chars.peek().and_then(|(c, _)| c.to_digit(10)) {
+                    number = number
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(digit as u64))
+                        .ok_or(ExpressionError::new(ErrorKind::Overflow, column))?;
+                    chars.next();
                 }
+                TokenKind::Number(number)
             }
+        };
+        tokens.push(Token { kind, column });
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+// The binding power and associativity of every operator. Operators with a higher binding power
+// are applied first, operators missing from the table are rejected by the parser
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    entries: Vec<(Operator, u8, Associativity)>,
+}
+
+impl OperatorTable {
+    pub fn new(entries: &[(Operator, u8, Associativity)]) -> Self {
+        OperatorTable {
+            entries: entries.to_vec(),
         }
     }
 
-    Some(acc)
+    // Addition and multiplication are evaluated left to right
+    pub fn equal_precedence() -> Self {
+        Self::new(&[
+            (Operator::Add, 1, Associativity::Left),
+            (Operator::Mul, 1, Associativity::Left),
+        ])
+    }
+
+    // Addition is evaluated before multiplication
+    pub fn addition_first() -> Self {
+        Self::new(&[
+            (Operator::Add, 2, Associativity::Left),
+            (Operator::Mul, 1, Associativity::Left),
+        ])
+    }
+
+    fn get(&self, operator: Operator) -> Option<(u8, Associativity)> {
+        self.entries
+            .iter()
+            .find(|(op, _, _)| *op == operator)
+            .map(|&(_, power, associativity)| (power, associativity))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(u64),
+    Binary {
+        operator: Operator,
+        column: usize,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
 }
 
-fn eval2<'a, S>(s: S) -> Option<u64>
+impl Expr {
+    pub fn eval(&self) -> Result<u64, ExpressionError> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Binary {
+                operator,
+                column,
+                lhs,
+                rhs,
+            } => operator
+                .apply(lhs.eval()?, rhs.eval()?)
+                .ok_or(ExpressionError::new(ErrorKind::Overflow, *column)),
+        }
+    }
+}
+
+// Writes the expression with every operation in parentheses, which shows the order it is
+// evaluated in
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Binary {
+                operator, lhs, rhs, ..
+            } => write!(f, "({} {} {})", lhs, operator, rhs),
+        }
+    }
+}
+
+// A Pratt parser, which parses operators with a binding power of at least `min_power` on every
+// level of recursion
+struct Parser<'t, I>
 where
-    S: AsRef<str> + 'a,
+    I: Iterator<Item = Token>,
 {
-    let line = s.as_ref();
-    let mut nums = vec![];
-    let mut level = 0;
-    let mut parens_expr = String::new();
-    let mut acc = 0;
-    for c in line.chars() {
-        match c {
-            ' ' | '+' => continue,
-            '*' => {
-                if level == 0 {
-                    nums.push(acc);
-                    acc = 0;
-                } else {
-                    parens_expr.push(c);
-                }
-            }
-            '(' => {
-                if level > 0 {
-                    parens_expr.push(c);
-                }
-                level += 1;
-            }
-            ')' => {
-                level -= 1;
-                if level > 0 {
-                    parens_expr.push(c);
-                } else {
-                    if let Some(num) = eval2(&parens_expr) {
-                        acc += num;
-                    }
-                    parens_expr.clear();
+    tokens: Peekable<I>,
+    table: &'t OperatorTable,
+    end: usize,
+}
+
+impl<'t, I> Parser<'t, I>
+where
+    I: Iterator<Item = Token>,
+{
+    fn primary(&mut self) -> Result<Expr, ExpressionError> {
+        let token = self
+            .tokens
+            .next()
+            .ok_or(ExpressionError::new(ErrorKind::UnexpectedEnd, self.end))?;
+
+        match token.kind {
+            TokenKind::Number(n) => Ok(Expr::Number(n)),
+            TokenKind::LeftParen => {
+                let expr = self.expression(0)?;
+                match self.tokens.next() {
+                    Some(Token {
+                        kind: TokenKind::RightParen,
+                        ..
+                    }) => Ok(expr),
+                    _ => Err(ExpressionError::new(ErrorKind::UnclosedParen, token.column)),
                 }
             }
-            _ => {
-                if level == 0 {
-                    acc += c.to_digit(10)? as u64;
-                } else {
-                    parens_expr.push(c);
+            _ => Err(ExpressionError::new(
+                ErrorKind::UnexpectedToken,
+                token.column,
+            )),
+        }
+    }
+
+    // The minimum power is wider than the binding powers, so that it can go one above the
+    // highest binding power for left associative operators
+    fn expression(&mut self, min_power: u16) -> Result<Expr, ExpressionError> {
+        let mut lhs = self.primary()?;
+
+        while let Some(&token) = self.tokens.peek() {
+            let operator = match token.kind {
+                TokenKind::Operator(operator) => operator,
+                // The caller decides whether a closing parenthesis is expected here
+                TokenKind::RightParen => break,
+                _ => {
+                    return Err(ExpressionError::new(
+                        ErrorKind::UnexpectedToken,
+                        token.column,
+                    ))
                 }
+            };
+
+            let (power, associativity) = self.table.get(operator).ok_or(ExpressionError::new(
+                ErrorKind::UnknownOperator,
+                token.column,
+            ))?;
+            let power = u16::from(power);
+            if power < min_power {
+                break;
             }
+            self.tokens.next();
+
+            let rhs = match associativity {
+                Associativity::Left => self.expression(power + 1)?,
+                Associativity::Right => self.expression(power)?,
+            };
+            lhs = Expr::Binary {
+                operator,
+                column: token.column,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
         }
+
+        Ok(lhs)
     }
-    nums.push(acc);
+}
+
+pub fn parse(line: &str, table: &OperatorTable) -> Result<Expr, ExpressionError> {
+    let mut parser = Parser {
+        tokens: tokenize(line)?.into_iter().peekable(),
+        table,
+        end: line.chars().count() + 1,
+    };
+    let expr = parser.expression(0)?;
 
-    Some(nums.iter().product())
+    // Everything has to be parsed, the only token that can be left over is an unmatched `)`
+    match parser.tokens.next() {
+        Some(token) => Err(ExpressionError::new(
+            ErrorKind::UnexpectedToken,
+            token.column,
+        )),
+        None => Ok(expr),
+    }
 }
 
-pub fn part1<'a, I, S>(lines: I) -> Option<u64>
+fn sum<'a, I, S>(lines: I, table: &OperatorTable) -> Result<u64, ExpressionError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut sum = 0;
-    for line in lines.into_iter() {
-        sum += eval(line)?;
+    let mut sum = 0u64;
+    for line in lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+    {
+        let value = parse(line, table)?.eval()?;
+        sum = sum
+            .checked_add(value)
+            .ok_or(ExpressionError::new(ErrorKind::Overflow, 1))?;
     }
 
-    Some(sum)
+    Ok(sum)
 }
 
-pub fn part2<'a, I, S>(lines: I) -> Option<u64>
+pub fn part1<'a, I, S>(lines: I) -> Result<u64, ExpressionError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut sum = 0;
-    for line in lines.into_iter() {
-        sum += eval2(line)?;
-    }
+    sum(lines, &OperatorTable::equal_precedence())
+}
 
-    Some(sum)
+pub fn part2<'a, I, S>(lines: I) -> Result<u64, ExpressionError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    sum(lines, &OperatorTable::addition_first())
 }
 
 #[cfg(test)]
@@ -166,4 +371,96 @@ mod tests {
             part2(&["((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"]).unwrap()
         );
     }
+
+    #[test]
+    fn pretty_print_test() {
+        let line = "2 * 3 + (4 * 5) + 12";
+
+        assert_eq!(
+            parse(line, &OperatorTable::equal_precedence())
+                .unwrap()
+                .to_string(),
+            "(((2 * 3) + (4 * 5)) + 12)"
+        );
+        assert_eq!(
+            parse(line, &OperatorTable::addition_first())
+                .unwrap()
+                .to_string(),
+            "(2 * ((3 + (4 * 5)) + 12))"
+        );
+
+        // Multiplication first, and right associative
+        let table = OperatorTable::new(&[
+            (Operator::Add, 1, Associativity::Right),
+            (Operator::Mul, 2, Associativity::Right),
+        ]);
+        assert_eq!(
+            parse("1 + 2 * 3 + 4", &table).unwrap().to_string(),
+            "(1 + ((2 * 3) + 4))"
+        );
+    }
+
+    #[test]
+    fn highest_power_test() {
+        // Left associativity still holds at the highest binding power
+        let table = OperatorTable::new(&[
+            (Operator::Add, u8::MAX, Associativity::Left),
+            (Operator::Mul, u8::MAX, Associativity::Left),
+        ]);
+        let expr = parse("2 * 3 + 4 * 5", &table).unwrap();
+
+        assert_eq!(expr.to_string(), "(((2 * 3) + 4) * 5)");
+        assert_eq!(expr.eval(), Ok(50));
+
+        let table = OperatorTable::new(&[
+            (Operator::Add, u8::MAX, Associativity::Left),
+            (Operator::Mul, u8::MAX - 1, Associativity::Left),
+        ]);
+        assert_eq!(
+            parse("2 * 3 + 4 + 5", &table).unwrap().to_string(),
+            "(2 * ((3 + 4) + 5))"
+        );
+    }
+
+    #[test]
+    fn error_test() {
+        let table = OperatorTable::equal_precedence();
+        let error = |line| parse(line, &table).unwrap_err();
+
+        assert_eq!(
+            error("1 + x"),
+            ExpressionError::new(ErrorKind::UnexpectedChar('x'), 5)
+        );
+        assert_eq!(
+            error("1 + (2 * 3"),
+            ExpressionError::new(ErrorKind::UnclosedParen, 5)
+        );
+        assert_eq!(
+            error("1 + 2)"),
+            ExpressionError::new(ErrorKind::UnexpectedToken, 6)
+        );
+        assert_eq!(
+            error("1 2"),
+            ExpressionError::new(ErrorKind::UnexpectedToken, 3)
+        );
+        assert_eq!(
+            error("1 *"),
+            ExpressionError::new(ErrorKind::UnexpectedEnd, 4)
+        );
+        assert_eq!(
+            parse("1 * 2", &OperatorTable::new(&[])).unwrap_err(),
+            ExpressionError::new(ErrorKind::UnknownOperator, 3)
+        );
+        assert_eq!(
+            parse("99999999999 * 99999999999", &table)
+                .unwrap()
+                .eval()
+                .unwrap_err(),
+            ExpressionError::new(ErrorKind::Overflow, 13)
+        );
+        assert_eq!(
+            error("1 + (2 * 3").annotate("1 + (2 * 3"),
+            "1 + (2 * 3\n    ^"
+        );
+    }
 }
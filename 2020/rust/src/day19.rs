@@ -1,22 +1,25 @@
-use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::error::Error;
 use std::fmt;
 
 #[derive(Debug)]
-pub enum ParseRuleError {
+pub enum GrammarError {
     InvalidFormat(&'static str),
+    MissingRule(u8),
+    LeftRecursion(u8),
 }
 
-impl fmt::Display for ParseRuleError {
+impl fmt::Display for GrammarError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Self::InvalidFormat(s) => write!(f, "Invalid format: {}", s),
+            Self::MissingRule(n) => write!(f, "Missing rule: {}", n),
+            Self::LeftRecursion(n) => write!(f, "Left recursive rule: {}", n),
         }
     }
 }
 
-impl Error for ParseRuleError {}
+impl Error for GrammarError {}
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 enum Symbol {
@@ -33,175 +36,346 @@ impl fmt::Debug for Symbol {
     }
 }
 
-#[derive(Debug, Clone)]
-enum Product {
-    List(Vec<Product>),
-    Branch(Vec<Product>, Vec<Product>),
-    Symbol(Symbol),
-}
-
+// Every rule is a list of alternatives, and every alternative a sequence of symbols
 #[derive(Default, Debug)]
 struct Grammar {
-    rules: HashMap<Symbol, Product>,
+    rules: HashMap<u8, Vec<Vec<Symbol>>>,
 }
 
 impl Grammar {
-    fn add_rule(&mut self, line: &str) -> Result<(), ParseRuleError> {
-        let mut words = line.split(' ');
-        let (mut list1, mut list2) = (vec![], vec![]);
-        let mut is_branch = false;
-
-        let start_symbol = words
-            .next()
-            .ok_or(ParseRuleError::InvalidFormat("missing start symbol"))?
-            .strip_suffix(':')
-            .ok_or(ParseRuleError::InvalidFormat("missing colon"))?
+    // Adds a rule like `8: 42 | 42 8`, replacing the rule with the same number if there is one
+    fn add_rule(&mut self, line: &str) -> Result<(), GrammarError> {
+        let (number, alternatives) = line
+            .split_once(": ")
+            .ok_or(GrammarError::InvalidFormat("missing colon"))?;
+        let number = number
             .parse::<u8>()
-            .map_err(|_| ParseRuleError::InvalidFormat("not an integer"))?;
-        let start_symbol = Symbol::Nonterminal(start_symbol);
-
-        for word in words {
-            if let Ok(num) = word.parse::<u8>() {
-                if is_branch {
-                    list2.push(Product::Symbol(Symbol::Nonterminal(num)));
+            .map_err(|_| GrammarError::InvalidFormat("not an integer"))?;
+
+        let alternatives = alternatives
+            .split('|')
+            .map(|alternative| {
+                let symbols = alternative
+                    .split_whitespace()
+                    .map(|word| {
+                        if let Ok(n) = word.parse::<u8>() {
+                            Ok(Symbol::Nonterminal(n))
+                        } else {
+                            let mut chars = word.chars();
+                            match (chars.next(), chars.next(), chars.next(), chars.next()) {
+                                (Some('"'), Some(c), Some('"'), None) => Ok(Symbol::Terminal(c)),
+                                _ => Err(GrammarError::InvalidFormat("invalid symbol")),
+                            }
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if symbols.is_empty() {
+                    Err(GrammarError::InvalidFormat("empty alternative"))
                 } else {
-                    list1.push(Product::Symbol(Symbol::Nonterminal(num)));
+                    Ok(symbols)
                 }
-            } else if word.starts_with("\"") {
-                let character = Symbol::Terminal(
-                    word.chars()
-                        .skip(1)
-                        .next()
-                        .ok_or(ParseRuleError::InvalidFormat("missing char"))?,
-                );
-                if is_branch {
-                    list2.push(Product::Symbol(character));
-                } else {
-                    list1.push(Product::Symbol(character));
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.rules.insert(number, alternatives);
+        Ok(())
+    }
+
+    // The fewest characters every rule matches. Missing rules count as 0, so that matching
+    // still gets to them and reports them
+    fn min_lens(&self) -> Vec<usize> {
+        let mut min_lens = vec![0; 256];
+        for &rule in self.rules.keys() {
+            min_lens[rule as usize] = usize::MAX;
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (&rule, alternatives) in self.rules.iter() {
+                let min_len = alternatives
+                    .iter()
+                    .map(|alternative| min_len(alternative, &min_lens))
+                    .min()
+                    .unwrap_or(usize::MAX);
+
+                if min_len < min_lens[rule as usize] {
+                    min_lens[rule as usize] = min_len;
+                    changed = true;
                 }
-            } else if word == "|" {
-                is_branch = true;
             }
         }
-        if is_branch {
-            self.rules
-                .insert(start_symbol, Product::Branch(list1.clone(), list2.clone()));
-        } else {
-            self.rules
-                .insert(start_symbol, Product::List(list1.clone()));
-        }
-        Ok(())
+
+        min_lens
     }
+}
 
-    fn get_product(&self, n: u8) -> &Product {
-        self.rules.get(&Symbol::Nonterminal(n)).unwrap()
+fn symbol_len(symbol: Symbol, min_lens: &[usize]) -> usize {
+    match symbol {
+        Symbol::Terminal(_) => 1,
+        Symbol::Nonterminal(n) => min_lens[n as usize],
     }
+}
 
-    fn to_regex(&self, start: &Product) -> String {
-        match start {
-            Product::List(l) => l
-                .iter()
-                .map(|p| self.to_regex(&p).chars().collect::<Vec<_>>())
-                .flatten()
-                .collect::<String>(),
-            Product::Branch(l, r) => {
-                let left = l
-                    .iter()
-                    .map(|p| self.to_regex(&p).chars().collect::<Vec<_>>())
-                    .flatten()
-                    .collect::<String>();
-                let right = r
-                    .iter()
-                    .map(|p| self.to_regex(&p).chars().collect::<Vec<_>>())
-                    .flatten()
-                    .collect::<String>();
-                format!("({}|{})", left, right)
-            }
-            Product::Symbol(s) => match s {
-                Symbol::Terminal(c) => format!("{}", c),
-                Symbol::Nonterminal(_) => match self.rules.get(s) {
-                    Some(p) => self.to_regex(p),
-                    None => "".to_string(),
-                },
-            },
+fn min_len(symbols: &[Symbol], min_lens: &[usize]) -> usize {
+    symbols.iter().fold(0, |sum, &symbol| {
+        sum.saturating_add(symbol_len(symbol, min_lens))
+    })
+}
+
+// A set of positions in a message
+trait Positions: Clone + Default {
+    // The longest message the set can hold the positions of
+    const MAX_LEN: usize;
+
+    fn insert(&mut self, position: usize);
+    fn union(&mut self, other: &Self);
+    fn is_empty(&self) -> bool;
+    fn contains(&self, position: usize) -> bool;
+    fn pop_first(&mut self) -> Option<usize>;
+    // Removes the positions after the given one
+    fn keep_up_to(&mut self, last: usize);
+}
+
+// A bit for every position, which fits almost every message
+impl Positions for u128 {
+    const MAX_LEN: usize = 127;
+
+    fn insert(&mut self, position: usize) {
+        *self |= 1 << position;
+    }
+
+    fn union(&mut self, other: &Self) {
+        *self |= other;
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == 0
+    }
+
+    fn contains(&self, position: usize) -> bool {
+        *self >> position & 1 == 1
+    }
+
+    fn pop_first(&mut self) -> Option<usize> {
+        if *self == 0 {
+            return None;
+        }
+
+        let position = self.trailing_zeros() as usize;
+        *self &= *self - 1;
+        Some(position)
+    }
+
+    fn keep_up_to(&mut self, last: usize) {
+        if last < Self::MAX_LEN {
+            *self &= (1 << (last + 1)) - 1;
         }
     }
 }
 
-pub fn part1<'a, I, S>(lines: I) -> Result<usize, ParseRuleError>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut grammar: Grammar = Default::default();
-    let mut iter = lines.into_iter();
+impl Positions for BTreeSet<usize> {
+    const MAX_LEN: usize = usize::MAX;
 
-    loop {
-        if let Some(line) = iter.next() {
-            let line = line.as_ref();
+    fn insert(&mut self, position: usize) {
+        BTreeSet::insert(self, position);
+    }
 
-            if line == "" {
-                break;
-            }
+    fn union(&mut self, other: &Self) {
+        self.extend(other);
+    }
+
+    fn is_empty(&self) -> bool {
+        BTreeSet::is_empty(self)
+    }
+
+    fn contains(&self, position: usize) -> bool {
+        BTreeSet::contains(self, &position)
+    }
+
+    fn pop_first(&mut self) -> Option<usize> {
+        BTreeSet::pop_first(self)
+    }
 
-            grammar.add_rule(line)?;
+    fn keep_up_to(&mut self, last: usize) {
+        self.split_off(&(last + 1));
+    }
+}
+
+// A backtracking matcher, which finds every position a rule can end at when it starts at a
+// given position. Rules can be recursive in any way, except for left recursion, which would
+// never consume any input.
+//
+// The same matcher is used for every message, so its memo is only allocated once. Alternatives
+// that can't fit in the rest of the message are skipped, which is what keeps recursive rules
+// like `8: 42 | 42 8` from trying every number of repeats at every position
+struct Matcher<'g, P> {
+    // The alternatives of every rule, indexed by rule
+    rules: Vec<Option<&'g [Vec<Symbol>]>>,
+    min_lens: Vec<usize>,
+    input: Vec<char>,
+    message: usize,
+    // Both indexed by rule and start position. An entry is only valid if its stamp is twice the
+    // current message, while its ends are being found, or one more than that once they are found
+    stamps: Vec<usize>,
+    memo: Vec<P>,
+}
+
+impl<'g, P: Positions> Matcher<'g, P> {
+    fn new(grammar: &'g Grammar) -> Self {
+        let mut rules = vec![None; 256];
+        for (&rule, alternatives) in grammar.rules.iter() {
+            rules[rule as usize] = Some(alternatives.as_slice());
+        }
+
+        Self {
+            rules,
+            min_lens: grammar.min_lens(),
+            input: vec![],
+            message: 0,
+            stamps: vec![],
+            memo: vec![],
         }
     }
 
-    let re = Regex::new(&format!("^{}$", grammar.to_regex(grammar.get_product(0)))).unwrap();
+    fn matches(&mut self, message: &str) -> Result<bool, GrammarError> {
+        self.input.clear();
+        self.input.extend(message.chars());
+        self.message += 1;
 
-    Ok(iter.filter(|&l| re.is_match(l.as_ref())).count())
+        let len = self.input.len();
+        if len > P::MAX_LEN {
+            return Err(GrammarError::InvalidFormat("message too long"));
+        }
+        if self.memo.len() < 256 * (len + 1) {
+            self.stamps.resize(256 * (len + 1), 0);
+            self.memo.resize(256 * (len + 1), P::default());
+        }
+
+        Ok(self.ends(0, 0)?.contains(len))
+    }
+
+    fn ends(&mut self, rule: u8, start: usize) -> Result<P, GrammarError> {
+        let len = self.input.len();
+        let index = rule as usize * (len + 1) + start;
+
+        let stamp = 2 * self.message;
+        if self.stamps[index] == stamp + 1 {
+            return Ok(self.memo[index].clone());
+        }
+        // Getting back to the same rule without consuming anything
+        if self.stamps[index] == stamp {
+            return Err(GrammarError::LeftRecursion(rule));
+        }
+        self.stamps[index] = stamp;
+
+        let alternatives = self.rules[rule as usize].ok_or(GrammarError::MissingRule(rule))?;
+        let mut ends = P::default();
+
+        for alternative in alternatives {
+            let mut positions = P::default();
+            positions.insert(start);
+            // The fewest characters the symbols from the current one on still need
+            let mut rest = min_len(alternative, &self.min_lens);
+
+            for &symbol in alternative {
+                match len.checked_sub(rest) {
+                    Some(last) => positions.keep_up_to(last),
+                    None => positions = P::default(),
+                }
+                if positions.is_empty() {
+                    break;
+                }
+
+                let mut next = P::default();
+                while let Some(position) = positions.pop_first() {
+                    match symbol {
+                        Symbol::Terminal(c) => {
+                            if self.input.get(position) == Some(&c) {
+                                next.insert(position + 1);
+                            }
+                        }
+                        Symbol::Nonterminal(n) => next.union(&self.ends(n, position)?),
+                    }
+                }
+
+                positions = next;
+                rest -= symbol_len(symbol, &self.min_lens);
+            }
+
+            ends.union(&positions);
+        }
+
+        self.stamps[index] = stamp + 1;
+        self.memo[index] = ends.clone();
+        Ok(ends)
+    }
 }
 
-pub fn part2<'a, I, S>(lines: I) -> Result<usize, ParseRuleError>
+// Reads the rules up to the first empty line, and the messages after it. Without an empty line
+// there are no messages
+fn parse_input<'a, I, S>(lines: I) -> Result<(Grammar, Vec<&'a str>), GrammarError>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
     let mut grammar: Grammar = Default::default();
-    let mut iter = lines.into_iter();
+    let mut iter = lines.into_iter().map(|l| l.as_ref());
 
-    loop {
-        if let Some(line) = iter.next() {
-            let line = line.as_ref();
+    for line in iter.by_ref() {
+        if line.is_empty() {
+            break;
+        }
 
-            if line == "" {
-                break;
-            }
+        grammar.add_rule(line)?;
+    }
+
+    Ok((grammar, iter.filter(|l| !l.is_empty()).collect()))
+}
+
+fn count_matches(grammar: &Grammar, messages: &[&str]) -> Result<usize, GrammarError> {
+    let mut short = Matcher::<u128>::new(grammar);
+    let mut long = None;
 
-            grammar.add_rule(line)?;
+    let mut count = 0;
+    for message in messages {
+        let matches = if message.chars().count() <= <u128 as Positions>::MAX_LEN {
+            short.matches(message)?
+        } else {
+            long.get_or_insert_with(|| Matcher::<BTreeSet<usize>>::new(grammar))
+                .matches(message)?
+        };
+
+        if matches {
+            count += 1;
         }
     }
 
-    let re42 = grammar.to_regex(grammar.get_product(42));
-    let re31 = grammar.to_regex(grammar.get_product(31));
+    Ok(count)
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<usize, GrammarError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let (grammar, messages) = parse_input(lines)?;
 
-    let re42 = Regex::new(&re42).unwrap();
-    let re31 = Regex::new(&re31).unwrap();
+    count_matches(&grammar, &messages)
+}
 
-    Ok(iter
-        .filter(|&l| {
-            let l = l.as_ref();
-            let mut offset = 0;
-            let (mut m42, mut m31) = (0, 0);
-            while let Some(m) = re42.find_at(l, offset) {
-                if m.start() != offset {
-                    break;
-                }
-                offset += m.as_str().len();
-                m42 += 1;
-            }
-            while let Some(m) = re31.find_at(l, offset) {
-                if m.start() != offset {
-                    break;
-                }
-                offset += m.as_str().len();
-                m31 += 1;
-            }
+pub fn part2<'a, I, S>(lines: I) -> Result<usize, GrammarError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let (mut grammar, messages) = parse_input(lines)?;
+    grammar.add_rule("8: 42 | 42 8")?;
+    grammar.add_rule("11: 42 31 | 42 11 31")?;
 
-            m42 > m31 && m31 > 0 && offset == l.len()
-        })
-        .count())
+    count_matches(&grammar, &messages)
 }
 
 #[cfg(test)]
@@ -271,4 +445,76 @@ mod tests {
 
         assert_eq!(result, 12);
     }
+
+    #[test]
+    fn missing_blank_line_test() {
+        assert_eq!(part1(&EXAMPLE[..31]).unwrap(), 0);
+        assert_eq!(part2(&EXAMPLE[..31]).unwrap(), 0);
+    }
+
+    fn recursive_rules<P: Positions>() {
+        // Balanced parentheses, recursing in the middle and on the right
+        let grammar = &["0: 1 2 | 1 0 2 | 1 2 0 | 1 0 2 0", "1: \"(\"", "2: \")\""];
+        let (grammar, _) = parse_input(grammar).unwrap();
+        let mut matcher = Matcher::<P>::new(&grammar);
+
+        // The same matcher works for every message, whatever their lengths
+        assert!(matcher.matches("(()())()").unwrap());
+        assert!(matcher.matches("()").unwrap());
+        assert!(!matcher.matches("(()").unwrap());
+        assert!(!matcher.matches("())(").unwrap());
+        assert!(matcher.matches("(()())()").unwrap());
+
+        let (grammar, _) = parse_input(&["0: 0 1 | 1", "1: \"a\""]).unwrap();
+        assert!(matches!(
+            Matcher::<P>::new(&grammar).matches("aa"),
+            Err(GrammarError::LeftRecursion(0))
+        ));
+
+        let (grammar, _) = parse_input(&["0: 1 2", "1: \"a\""]).unwrap();
+        assert!(matches!(
+            Matcher::<P>::new(&grammar).matches("ab"),
+            Err(GrammarError::MissingRule(2))
+        ));
+    }
+
+    #[test]
+    fn recursive_rules_test() {
+        recursive_rules::<u128>();
+        recursive_rules::<BTreeSet<usize>>();
+    }
+
+    #[test]
+    fn long_messages_test() {
+        let grammar = &["0: 1 2 | 1 0 2 | 1 2 0 | 1 0 2 0", "1: \"(\"", "2: \")\""];
+        let (grammar, _) = parse_input(grammar).unwrap();
+        let nested = "(".repeat(100) + &")".repeat(100);
+        let unbalanced = "(".repeat(100) + &")".repeat(99);
+        let repeated = "()".repeat(100);
+        let messages = ["()", &repeated, &nested, &unbalanced, "(("];
+
+        assert_eq!(count_matches(&grammar, &messages).unwrap(), 3);
+        assert!(matches!(
+            Matcher::<u128>::new(&grammar).matches(&nested),
+            Err(GrammarError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn min_lens_test() {
+        let (grammar, _) = parse_input(EXAMPLE).unwrap();
+        let min_lens = grammar.min_lens();
+        assert_eq!((min_lens[1], min_lens[42], min_lens[0]), (1, 5, 15));
+
+        let (mut grammar, _) = parse_input(EXAMPLE).unwrap();
+        grammar.add_rule("8: 42 | 42 8").unwrap();
+        grammar.add_rule("11: 42 31 | 42 11 31").unwrap();
+        let min_lens = grammar.min_lens();
+        assert_eq!((min_lens[8], min_lens[11], min_lens[0]), (5, 10, 15));
+
+        // Rules that never end can't match anything, and missing rules are left to the matcher
+        let (grammar, _) = parse_input(&["0: 1 0", "1: 2"]).unwrap();
+        let min_lens = grammar.min_lens();
+        assert_eq!((min_lens[0], min_lens[1], min_lens[2]), (usize::MAX, 0, 0));
+    }
 }
@@ -0,0 +1,606 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+const SEA_MONSTER: &[&str] = &[
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
+#[derive(Debug)]
+pub enum JigsawError {
+    Format,
+    Assembly,
+}
+
+impl fmt::Display for JigsawError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "JigsawError::Format"),
+            Self::Assembly => write!(f, "JigsawError::Assembly"),
+        }
+    }
+}
+
+impl Error for JigsawError {}
+
+// A square image, which can be rotated and flipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    size: usize,
+    cells: Vec<bool>,
+}
+
+impl Grid {
+    pub fn from_rows(rows: &[Vec<bool>]) -> Result<Self, JigsawError> {
+        let size = rows.len();
+        if rows.iter().any(|r| r.len() != size) {
+            return Err(JigsawError::Format);
+        }
+
+        Ok(Self {
+            size,
+            cells: rows.concat(),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.size + x]
+    }
+
+    // Clockwise
+    pub fn rotate(&self) -> Self {
+        let n = self.size;
+        let cells = (0..n * n)
+            .map(|i| {
+                let (x, y) = (i % n, i / n);
+                self.get(y, n - 1 - x)
+            })
+            .collect();
+
+        Self { size: n, cells }
+    }
+
+    // Mirrored along the vertical axis
+    pub fn flip(&self) -> Self {
+        let n = self.size;
+        let cells = (0..n * n)
+            .map(|i| {
+                let (x, y) = (i % n, i / n);
+                self.get(n - 1 - x, y)
+            })
+            .collect();
+
+        Self { size: n, cells }
+    }
+
+    // All 8 orientations: the 4 rotations, then the 4 rotations of the flipped grid
+    pub fn orientations(&self) -> Vec<Self> {
+        let mut orientations = Vec::with_capacity(8);
+        for start in [self.clone(), self.flip()] {
+            let mut grid = start;
+            for _ in 0..4 {
+                let next = grid.rotate();
+                orientations.push(grid);
+                grid = next;
+            }
+        }
+
+        orientations
+    }
+
+    // Edges are read left to right and top to bottom, so that the right edge of a grid must equal
+    // the left edge of its neighbor, and the bottom edge must equal the top edge of the one below
+    fn edges(&self) -> Edges {
+        let n = self.size;
+        let read =
+            |cells: &mut dyn Iterator<Item = bool>| cells.fold(0, |acc, c| (acc << 1) | c as u16);
+
+        Edges {
+            top: read(&mut (0..n).map(|x| self.get(x, 0))),
+            right: read(&mut (0..n).map(|y| self.get(n - 1, y))),
+            bottom: read(&mut (0..n).map(|x| self.get(x, n - 1))),
+            left: read(&mut (0..n).map(|y| self.get(0, y))),
+        }
+    }
+
+    fn without_border(&self) -> Vec<Vec<bool>> {
+        let inner = 1..self.size.saturating_sub(1);
+        inner
+            .clone()
+            .map(|y| inner.clone().map(|x| self.get(x, y)).collect())
+            .collect()
+    }
+
+    fn count(&self) -> usize {
+        self.cells.iter().filter(|&&c| c).count()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edges {
+    top: u16,
+    right: u16,
+    bottom: u16,
+    left: u16,
+}
+
+// A piece of the image. Its edges are stored as u16, and it needs to keep something after
+// stripping its border
+#[derive(Debug, Clone)]
+pub struct Tile {
+    id: u64,
+    grid: Grid,
+}
+
+// An edge is the same whichever way its tile is flipped, so it's identified by the smaller of its
+// two readings
+fn signature(edge: u16, len: usize) -> u16 {
+    let reversed = edge.reverse_bits() >> (16 - len);
+    edge.min(reversed)
+}
+
+impl Tile {
+    pub fn new(id: u64, grid: Grid) -> Result<Self, JigsawError> {
+        if !(3..=16).contains(&grid.size) {
+            return Err(JigsawError::Format);
+        }
+
+        Ok(Self { id, grid })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // The same tile in all 8 orientations, in the order of `Grid::orientations`
+    pub fn orientations(&self) -> Vec<Self> {
+        self.grid
+            .orientations()
+            .into_iter()
+            .map(|grid| Self { id: self.id, grid })
+            .collect()
+    }
+
+    fn signature(&self, edge: u16) -> u16 {
+        signature(edge, self.grid.size)
+    }
+
+    // The signatures of the top, right, bottom and left edges, which stay the same when the tile
+    // is flipped
+    pub fn signatures(&self) -> [u16; 4] {
+        let edges = self.grid.edges();
+        [edges.top, edges.right, edges.bottom, edges.left].map(|e| self.signature(e))
+    }
+}
+
+fn parse_tiles<'a, I, S>(lines: I) -> Result<Vec<Tile>, JigsawError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let mut tiles = vec![];
+    let mut id = None;
+    let mut rows = vec![];
+
+    // Chaining an empty line makes sure the last tile is finished as well
+    for line in lines.into_iter().map(|l| l.as_ref()).chain([""]) {
+        if let Some(rest) = line.strip_prefix("Tile ") {
+            let rest = rest.strip_suffix(':').ok_or(JigsawError::Format)?;
+            id = Some(rest.parse::<u64>().map_err(|_| JigsawError::Format)?);
+        } else if line.is_empty() {
+            if let Some(id) = id.take() {
+                tiles.push(Tile::new(id, Grid::from_rows(&rows)?)?);
+                rows.clear();
+            }
+        } else if id.is_some() {
+            rows.push(line.chars().map(|c| c == '#').collect());
+        } else {
+            return Err(JigsawError::Format);
+        }
+    }
+
+    // Edges of different tiles are only compared if they have the same length
+    let size = tiles.first().ok_or(JigsawError::Format)?.grid.size;
+    if tiles.iter().any(|t| t.grid.size != size) {
+        return Err(JigsawError::Format);
+    }
+
+    Ok(tiles)
+}
+
+struct Puzzle {
+    tiles: Vec<Tile>,
+    // Tile indices by edge signature
+    by_signature: HashMap<u16, Vec<usize>>,
+}
+
+impl Puzzle {
+    fn new(tiles: Vec<Tile>) -> Self {
+        let mut by_signature: HashMap<u16, Vec<usize>> = HashMap::new();
+        for (i, tile) in tiles.iter().enumerate() {
+            for signature in tile.signatures() {
+                by_signature.entry(signature).or_default().push(i);
+            }
+        }
+
+        Self {
+            tiles,
+            by_signature,
+        }
+    }
+
+    // Edges on the border of the image don't match any other tile
+    fn is_border(&self, tile: &Tile, edge: u16) -> bool {
+        self.by_signature[&tile.signature(edge)].len() == 1
+    }
+
+    fn corners(&self) -> Vec<usize> {
+        (0..self.tiles.len())
+            .filter(|&i| {
+                let tile = &self.tiles[i];
+                let edges = tile.grid.edges();
+                [edges.top, edges.right, edges.bottom, edges.left]
+                    .iter()
+                    .filter(|&&e| self.is_border(tile, e))
+                    .count()
+                    == 2
+            })
+            .collect()
+    }
+
+    // Finds an unused tile, oriented so that it has the given edge on the given side
+    fn find_neighbor(
+        &self,
+        used: &HashSet<usize>,
+        edge: u16,
+        side: impl Fn(&Edges) -> u16,
+    ) -> Option<(usize, Grid)> {
+        let len = self.tiles[0].grid.size;
+
+        self.by_signature
+            .get(&signature(edge, len))?
+            .iter()
+            .filter(|i| !used.contains(i))
+            .find_map(|&i| {
+                self.tiles[i]
+                    .grid
+                    .orientations()
+                    .into_iter()
+                    .find(|g| side(&g.edges()) == edge)
+                    .map(|g| (i, g))
+            })
+    }
+
+    // Places the tiles row by row, starting from a corner in the top left
+    fn assemble(&self) -> Result<Vec<Vec<Grid>>, JigsawError> {
+        let side = (self.tiles.len() as f64).sqrt() as usize;
+        if side * side != self.tiles.len() {
+            return Err(JigsawError::Assembly);
+        }
+
+        let &corner = self.corners().first().ok_or(JigsawError::Assembly)?;
+        let corner_tile = &self.tiles[corner];
+        let top_left = corner_tile
+            .grid
+            .orientations()
+            .into_iter()
+            .find(|g| {
+                let edges = g.edges();
+                self.is_border(corner_tile, edges.top) && self.is_border(corner_tile, edges.left)
+            })
+            .ok_or(JigsawError::Assembly)?;
+
+        let mut used = HashSet::from([corner]);
+        let mut rows: Vec<Vec<Grid>> = vec![vec![top_left]];
+
+        for y in 0..side {
+            if y > 0 {
+                let above = rows[y - 1][0].edges().bottom;
+                let (i, grid) = self
+                    .find_neighbor(&used, above, |e| e.top)
+                    .ok_or(JigsawError::Assembly)?;
+                used.insert(i);
+                rows.push(vec![grid]);
+            }
+
+            for x in 1..side {
+                let left = rows[y][x - 1].edges().right;
+                let (i, grid) = self
+                    .find_neighbor(&used, left, |e| e.left)
+                    .ok_or(JigsawError::Assembly)?;
+
+                if y > 0 && grid.edges().top != rows[y - 1][x].edges().bottom {
+                    return Err(JigsawError::Assembly);
+                }
+
+                used.insert(i);
+                rows[y].push(grid);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+fn stitch(placed: &[Vec<Grid>]) -> Result<Grid, JigsawError> {
+    let rows = placed
+        .iter()
+        .flat_map(|row| {
+            let stripped = row.iter().map(Grid::without_border).collect::<Vec<_>>();
+            (0..stripped[0].len())
+                .map(move |y| stripped.iter().flat_map(|s| s[y].clone()).collect())
+        })
+        .collect::<Vec<Vec<bool>>>();
+
+    Grid::from_rows(&rows)
+}
+
+fn sea_monster_offsets() -> Vec<(usize, usize)> {
+    SEA_MONSTER
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|&(_, c)| c == '#')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect()
+}
+
+// Cells that are part of a sea monster, in the first orientation where there are any
+fn find_sea_monsters(image: &Grid) -> HashSet<(usize, usize)> {
+    let offsets = sea_monster_offsets();
+    let width = SEA_MONSTER[0].len();
+    let height = SEA_MONSTER.len();
+
+    for grid in image.orientations() {
+        let mut cells = HashSet::new();
+
+        for y in 0..=grid.size.saturating_sub(height) {
+            for x in 0..=grid.size.saturating_sub(width) {
+                if offsets.iter().all(|&(dx, dy)| grid.get(x + dx, y + dy)) {
+                    cells.extend(offsets.iter().map(|&(dx, dy)| (x + dx, y + dy)));
+                }
+            }
+        }
+
+        if !cells.is_empty() {
+            return cells;
+        }
+    }
+
+    HashSet::new()
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<u64, JigsawError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let puzzle = Puzzle::new(parse_tiles(lines)?);
+    let corners = puzzle.corners();
+
+    if corners.len() != 4 {
+        return Err(JigsawError::Assembly);
+    }
+
+    Ok(corners.iter().map(|&i| puzzle.tiles[i].id).product())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<usize, JigsawError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let puzzle = Puzzle::new(parse_tiles(lines)?);
+    let image = stitch(&puzzle.assemble()?)?;
+    let sea_monsters = find_sea_monsters(&image);
+
+    Ok(image.count() - sea_monsters.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_rows(lines: &[&str]) -> Vec<Vec<bool>> {
+        lines
+            .iter()
+            .map(|l| l.chars().map(|c| c == '#').collect())
+            .collect()
+    }
+
+    static EXAMPLE: &[&str] = &[
+        "Tile 2311:",
+        "..##.#..#.",
+        "##..#.....",
+        "#...##..#.",
+        "####.#...#",
+        "##.##.###.",
+        "##...#.###",
+        ".#.#.#..##",
+        "..#....#..",
+        "###...#.#.",
+        "..###..###",
+        "",
+        "Tile 1951:",
+        "#.##...##.",
+        "#.####...#",
+        ".....#..##",
+        "#...######",
+        ".##.#....#",
+        ".###.#####",
+        "###.##.##.",
+        ".###....#.",
+        "..#.#..#.#",
+        "#...##.#..",
+        "",
+        "Tile 1171:",
+        "####...##.",
+        "#..##.#..#",
+        "##.#..#.#.",
+        ".###.####.",
+        "..###.####",
+        ".##....##.",
+        ".#...####.",
+        "#.##.####.",
+        "####..#...",
+        ".....##...",
+        "",
+        "Tile 1427:",
+        "###.##.#..",
+        ".#..#.##..",
+        ".#.##.#..#",
+        "#.#.#.##.#",
+        "....#...##",
+        "...##..##.",
+        "...#.#####",
+        ".#.####.#.",
+        "..#..###.#",
+        "..##.#..#.",
+        "",
+        "Tile 1489:",
+        "##.#.#....",
+        "..##...#..",
+        ".##..##...",
+        "..#...#...",
+        "#####...#.",
+        "#..#.#.#.#",
+        "...#.#.#..",
+        "##.#...##.",
+        "..##.##.##",
+        "###.##.#..",
+        "",
+        "Tile 2473:",
+        "#....####.",
+        "#..#.##...",
+        "#.##..#...",
+        "######.#.#",
+        ".#...#.#.#",
+        ".#########",
+        ".###.#..#.",
+        "########.#",
+        "##...##.#.",
+        "..###.#.#.",
+        "",
+        "Tile 2971:",
+        "..#.#....#",
+        "#...###...",
+        "#.#.###...",
+        "##.##..#..",
+        ".#####..##",
+        ".#..####.#",
+        "#..#.#..#.",
+        "..####.###",
+        "..#.#.###.",
+        "...#.#.#.#",
+        "",
+        "Tile 2729:",
+        "...#.#.#.#",
+        "####.#....",
+        "..#.#.....",
+        "....#..#.#",
+        ".##..##.#.",
+        ".#.####...",
+        "####.#.#..",
+        "##.####...",
+        "##..#.##..",
+        "#.##...##.",
+        "",
+        "Tile 3079:",
+        "#.#.#####.",
+        ".#..######",
+        "..#.......",
+        "######....",
+        "####.#..#.",
+        ".#...#.##.",
+        "#.#####.##",
+        "..#.###...",
+        "..#.......",
+        "..#.###...",
+        "",
+    ];
+
+    #[test]
+    fn orientations_test() {
+        let grid = Grid::from_rows(&to_rows(&["#..", "##.", "..."])).unwrap();
+        let orientations = grid.orientations();
+
+        assert_eq!(orientations.len(), 8);
+        assert_eq!(
+            orientations[1],
+            Grid::from_rows(&to_rows(&[".##", ".#.", "..."])).unwrap()
+        );
+        assert_eq!(
+            orientations[4],
+            Grid::from_rows(&to_rows(&["..#", ".##", "..."])).unwrap()
+        );
+        assert_eq!(grid.rotate().rotate().rotate().rotate(), grid);
+
+        // Flipping or rotating a tile moves its edges around, but doesn't change their signatures
+        let tile = Tile::new(7, grid).unwrap();
+        let mut signatures = tile.signatures();
+        signatures.sort_unstable();
+        for oriented in tile.orientations() {
+            let mut oriented_signatures = oriented.signatures();
+            oriented_signatures.sort_unstable();
+            assert_eq!(oriented.id(), 7);
+            assert_eq!(oriented_signatures, signatures);
+        }
+        assert!(Tile::new(7, Grid::from_rows(&to_rows(&["#."; 2])).unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_tiles_test() {
+        assert_eq!(parse_tiles(EXAMPLE).unwrap().len(), 9);
+
+        // Missing rows, too small, not square, and tiles of different sizes
+        for lines in [
+            &["Tile 1:", "", "Tile 2:", "..", ".."][..],
+            &["Tile 1:", "#."][..],
+            &["Tile 1:", "#.", "..", ".."][..],
+            &[
+                "Tile 1:", "...", "...", "...", "", "Tile 2:", "....", "....", "....", "....",
+            ][..],
+            &["...", "...", "..."][..],
+            &[][..],
+        ] {
+            assert!(matches!(parse_tiles(lines), Err(JigsawError::Format)));
+            assert!(part1(lines).is_err() && part2(lines).is_err());
+        }
+    }
+
+    #[test]
+    fn part1_test() {
+        let result = part1(EXAMPLE).unwrap();
+
+        assert_eq!(result, 20899048083289);
+    }
+
+    #[test]
+    fn assemble_test() {
+        let puzzle = Puzzle::new(parse_tiles(EXAMPLE).unwrap());
+        let assembled = stitch(&puzzle.assemble().unwrap()).unwrap();
+
+        assert_eq!(assembled.size, 24);
+        assert_eq!(assembled.count(), 273 + 2 * 15);
+    }
+
+    #[test]
+    fn part2_test() {
+        let result = part2(EXAMPLE).unwrap();
+
+        assert_eq!(result, 273);
+    }
+}
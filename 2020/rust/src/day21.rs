@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AllergenError {
+    Format,
+    Unresolved,
+}
+
+impl fmt::Display for AllergenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "AllergenError::Format"),
+            Self::Unresolved => write!(f, "AllergenError::Unresolved"),
+        }
+    }
+}
+
+impl Error for AllergenError {}
+
+#[derive(Debug)]
+struct Food<'a> {
+    ingredients: HashSet<&'a str>,
+    allergens: Vec<&'a str>,
+}
+
+fn parse_food(line: &str) -> Result<Food<'_>, AllergenError> {
+    let (ingredients, allergens) = match line.split_once(" (contains ") {
+        Some((ingredients, rest)) => {
+            let allergens = rest.strip_suffix(')').ok_or(AllergenError::Format)?;
+            (ingredients, allergens.split(", ").collect())
+        }
+        None => (line, vec![]),
+    };
+
+    let ingredients = ingredients.split_whitespace().collect::<HashSet<_>>();
+    if ingredients.is_empty() {
+        return Err(AllergenError::Format);
+    }
+
+    Ok(Food {
+        ingredients,
+        allergens,
+    })
+}
+
+fn parse_foods<'a, I, S>(lines: I) -> Result<Vec<Food<'a>>, AllergenError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+        .map(parse_food)
+        .collect()
+}
+
+// Every ingredient that could contain an allergen, keyed by allergen. An allergen is in exactly
+// one ingredient, so it can only be an ingredient that shows up in every food listing it
+fn candidates<'a>(foods: &[Food<'a>]) -> BTreeMap<&'a str, HashSet<&'a str>> {
+    let mut candidates: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+
+    for food in foods {
+        for &allergen in &food.allergens {
+            candidates
+                .entry(allergen)
+                .and_modify(|c| c.retain(|i| food.ingredients.contains(i)))
+                .or_insert_with(|| food.ingredients.clone());
+        }
+    }
+
+    candidates
+}
+
+// Repeatedly pins down allergens with a single candidate and removes that ingredient from the
+// candidates of every other allergen
+fn resolve<'a>(
+    mut candidates: BTreeMap<&'a str, HashSet<&'a str>>,
+) -> Result<BTreeMap<&'a str, &'a str>, AllergenError> {
+    let mut resolved = BTreeMap::new();
+
+    while !candidates.is_empty() {
+        let (allergen, ingredient) = candidates
+            .iter()
+            .find(|(_, c)| c.len() == 1)
+            .and_then(|(&a, c)| c.iter().next().map(|&i| (a, i)))
+            .ok_or(AllergenError::Unresolved)?;
+
+        candidates.remove(allergen);
+        for c in candidates.values_mut() {
+            c.remove(ingredient);
+        }
+        resolved.insert(allergen, ingredient);
+    }
+
+    Ok(resolved)
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<usize, AllergenError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let foods = parse_foods(lines)?;
+    let unsafe_ingredients = candidates(&foods)
+        .into_values()
+        .flatten()
+        .collect::<HashSet<_>>();
+
+    Ok(foods
+        .iter()
+        .flat_map(|f| f.ingredients.iter())
+        .filter(|i| !unsafe_ingredients.contains(*i))
+        .count())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<String, AllergenError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let foods = parse_foods(lines)?;
+    let resolved = resolve(candidates(&foods))?;
+
+    // The map is ordered by allergen, which is the canonical order
+    Ok(resolved.into_values().collect::<Vec<_>>().join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &[
+        "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)",
+        "trh fvjkl sbzzf mxmxvkd (contains dairy)",
+        "sqjhc fvjkl (contains soy)",
+        "sqjhc mxmxvkd sbzzf (contains fish)",
+    ];
+
+    #[test]
+    fn part1_test() {
+        assert_eq!(part1(EXAMPLE).unwrap(), 5);
+    }
+
+    #[test]
+    fn part2_test() {
+        assert_eq!(part2(EXAMPLE).unwrap(), "mxmxvkd,sqjhc,fvjkl");
+    }
+
+    #[test]
+    fn unresolved_test() {
+        let ambiguous: &[&str] = &["a b (contains dairy, fish)"];
+
+        assert!(matches!(part2(ambiguous), Err(AllergenError::Unresolved)));
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum CombatError {
+    Format,
+}
+
+impl fmt::Display for CombatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "CombatError::Format"),
+        }
+    }
+}
+
+impl Error for CombatError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Player {
+    One,
+    Two,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Deck(VecDeque<u8>);
+
+impl Deck {
+    fn put_bottom(&mut self, winner: u8, loser: u8) {
+        self.0.push_back(winner);
+        self.0.push_back(loser);
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn max(&self) -> Option<u8> {
+        self.0.iter().copied().max()
+    }
+
+    // A copy of the first `count` cards, used as the deck of a sub-game
+    fn top(&self, count: usize) -> Deck {
+        Deck(self.0.iter().take(count).copied().collect())
+    }
+
+    fn score(&self) -> u64 {
+        self.0
+            .iter()
+            .rev()
+            .zip(1..)
+            .map(|(&card, position)| card as u64 * position)
+            .sum()
+    }
+}
+
+fn parse_decks<'a, I, S>(lines: I) -> Result<(Deck, Deck), CombatError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let mut decks = vec![];
+
+    for line in lines.into_iter().map(|l| l.as_ref()) {
+        if line.starts_with("Player ") {
+            decks.push(Deck(VecDeque::new()));
+        } else if !line.is_empty() {
+            let card = line.parse().map_err(|_| CombatError::Format)?;
+            decks
+                .last_mut()
+                .ok_or(CombatError::Format)?
+                .0
+                .push_back(card);
+        }
+    }
+
+    if decks.len() != 2 {
+        return Err(CombatError::Format);
+    }
+
+    let p2 = decks.pop().unwrap();
+    let p1 = decks.pop().unwrap();
+    Ok((p1, p2))
+}
+
+// Draws the top card of both decks, unless one of them is already empty
+fn draw(p1: &mut Deck, p2: &mut Deck) -> Option<(u8, u8)> {
+    if p1.is_empty() || p2.is_empty() {
+        return None;
+    }
+
+    Some((p1.0.pop_front()?, p2.0.pop_front()?))
+}
+
+fn game_winner(p1: Deck, p2: Deck) -> (Player, Deck) {
+    if p1.is_empty() {
+        (Player::Two, p2)
+    } else {
+        (Player::One, p1)
+    }
+}
+
+fn play(mut p1: Deck, mut p2: Deck) -> (Player, Deck) {
+    loop {
+        let (c1, c2) = match draw(&mut p1, &mut p2) {
+            Some(cards) => cards,
+            None => return game_winner(p1, p2),
+        };
+
+        if c1 > c2 {
+            p1.put_bottom(c1, c2);
+        } else {
+            p2.put_bottom(c2, c1);
+        }
+    }
+}
+
+fn play_recursive(mut p1: Deck, mut p2: Deck, sub_game: bool) -> (Player, Deck) {
+    // Player 1 can never lose the highest card, so in a sub-game they either win outright or the
+    // game repeats, which they win as well. The top level game still has to be played out for
+    // the score
+    if sub_game && p1.max() > p2.max() {
+        return (Player::One, p1);
+    }
+
+    let mut seen = HashSet::new();
+
+    loop {
+        if !seen.insert((p1.clone(), p2.clone())) {
+            return (Player::One, p1);
+        }
+
+        let (c1, c2) = match draw(&mut p1, &mut p2) {
+            Some(cards) => cards,
+            None => return game_winner(p1, p2),
+        };
+
+        let winner = if p1.len() >= c1 as usize && p2.len() >= c2 as usize {
+            play_recursive(p1.top(c1 as usize), p2.top(c2 as usize), true).0
+        } else if c1 > c2 {
+            Player::One
+        } else {
+            Player::Two
+        };
+
+        match winner {
+            Player::One => p1.put_bottom(c1, c2),
+            Player::Two => p2.put_bottom(c2, c1),
+        }
+    }
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<u64, CombatError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let (p1, p2) = parse_decks(lines)?;
+    let (_, deck) = play(p1, p2);
+
+    Ok(deck.score())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<u64, CombatError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let (p1, p2) = parse_decks(lines)?;
+    let (_, deck) = play_recursive(p1, p2, false);
+
+    Ok(deck.score())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &[
+        "Player 1:",
+        "9",
+        "2",
+        "6",
+        "3",
+        "1",
+        "",
+        "Player 2:",
+        "5",
+        "8",
+        "4",
+        "7",
+        "10",
+    ];
+
+    #[test]
+    fn part1_test() {
+        assert_eq!(part1(EXAMPLE).unwrap(), 306);
+    }
+
+    #[test]
+    fn part2_test() {
+        assert_eq!(part2(EXAMPLE).unwrap(), 291);
+    }
+
+    #[test]
+    fn infinite_game_test() {
+        let lines: &[&str] = &["Player 1:", "43", "19", "", "Player 2:", "2", "29", "14"];
+        let (p1, p2) = parse_decks(lines).unwrap();
+        let (winner, deck) = play_recursive(p1, p2, false);
+
+        assert_eq!(winner, Player::One);
+        assert_eq!(deck, Deck(VecDeque::from(vec![43, 19])));
+    }
+}
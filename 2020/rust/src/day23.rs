@@ -0,0 +1,185 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum CupsError {
+    Format,
+}
+
+impl fmt::Display for CupsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "CupsError::Format"),
+        }
+    }
+}
+
+impl Error for CupsError {}
+
+// The cups in a circle, stored as a successor array: `next[label]` is the label of the cup
+// clockwise of the cup `label`. Labels start at 1, so index 0 is unused
+struct Cups {
+    next: Vec<u32>,
+    current: u32,
+}
+
+impl Cups {
+    // Places the given labels in order, followed by all labels after the largest one up to and
+    // including `total`
+    fn new(labels: &[u32], total: u32) -> Self {
+        let order = labels
+            .iter()
+            .copied()
+            .chain(labels.len() as u32 + 1..=total)
+            .collect::<Vec<_>>();
+        let mut next = vec![0; total as usize + 1];
+
+        for pair in order.windows(2) {
+            next[pair[0] as usize] = pair[1];
+        }
+        next[order[order.len() - 1] as usize] = order[0];
+
+        Cups {
+            next,
+            current: order[0],
+        }
+    }
+
+    fn max(&self) -> u32 {
+        self.next.len() as u32 - 1
+    }
+
+    fn step(&mut self) {
+        let a = self.next[self.current as usize];
+        let b = self.next[a as usize];
+        let c = self.next[b as usize];
+
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 {
+                self.max()
+            } else {
+                destination - 1
+            };
+            if destination != a && destination != b && destination != c {
+                break;
+            }
+        }
+
+        // Cut the three picked up cups out and splice them back in after the destination
+        self.next[self.current as usize] = self.next[c as usize];
+        self.next[c as usize] = self.next[destination as usize];
+        self.next[destination as usize] = a;
+        self.current = self.next[self.current as usize];
+    }
+
+    fn after_one(&self) -> impl Iterator<Item = u32> + '_ {
+        let mut label = 1;
+        std::iter::from_fn(move || {
+            label = self.next[label as usize];
+            if label == 1 {
+                None
+            } else {
+                Some(label)
+            }
+        })
+    }
+}
+
+fn parse_labels<'a, I, S>(lines: I) -> Result<Vec<u32>, CupsError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let line = lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .find(|l| !l.is_empty())
+        .ok_or(CupsError::Format)?;
+    let labels = line
+        .chars()
+        .map(|c| c.to_digit(10).ok_or(CupsError::Format))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The labels need to be exactly 1 to n for the successor array to work, and a move needs a
+    // destination cup besides the current cup and the three picked up ones
+    let mut sorted = labels.clone();
+    sorted.sort_unstable();
+    if labels.len() < 5 || !sorted.iter().copied().eq(1..=labels.len() as u32) {
+        return Err(CupsError::Format);
+    }
+
+    Ok(labels)
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<String, CupsError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let labels = parse_labels(lines)?;
+    let mut cups = Cups::new(&labels, labels.len() as u32);
+
+    for _ in 0..100 {
+        cups.step();
+    }
+
+    Ok(cups.after_one().map(|l| l.to_string()).collect())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<u64, CupsError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let labels = parse_labels(lines)?;
+    let mut cups = Cups::new(&labels, 1_000_000);
+
+    for _ in 0..10_000_000 {
+        cups.step();
+    }
+
+    Ok(cups.after_one().take(2).map(|l| l as u64).product())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &["389125467"];
+
+    #[test]
+    fn part1_test() {
+        assert_eq!(part1(EXAMPLE).unwrap(), "67384529");
+    }
+
+    #[test]
+    fn ten_moves_test() {
+        let labels = parse_labels(EXAMPLE).unwrap();
+        let mut cups = Cups::new(&labels, 9);
+
+        for _ in 0..10 {
+            cups.step();
+        }
+
+        assert_eq!(
+            cups.after_one().collect::<Vec<_>>(),
+            [9, 2, 6, 5, 8, 3, 7, 4]
+        );
+    }
+
+    #[test]
+    fn parse_labels_test() {
+        assert_eq!(parse_labels(&["51234"]), Ok(vec![5, 1, 2, 3, 4]));
+        assert_eq!(parse_labels(&["12"]), Err(CupsError::Format));
+        assert_eq!(parse_labels(&["4123"]), Err(CupsError::Format));
+        assert_eq!(parse_labels(&["12346"]), Err(CupsError::Format));
+        assert_eq!(parse_labels(&["12234"]), Err(CupsError::Format));
+        assert_eq!(part1(&["12"]), Err(CupsError::Format));
+    }
+
+    #[test]
+    fn part2_test() {
+        assert_eq!(part2(EXAMPLE).unwrap(), 149245887792);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::ops::Add;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ParseHexError {
+    Direction,
+}
+
+impl fmt::Display for ParseHexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Direction => write!(f, "ParseHexError::Direction"),
+        }
+    }
+}
+
+impl Error for ParseHexError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::East,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+        Direction::NorthEast,
+    ];
+
+    fn offset(self) -> Hex {
+        let (q, r) = match self {
+            Direction::East => (1, 0),
+            Direction::SouthEast => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (0, -1),
+            Direction::NorthEast => (1, -1),
+        };
+
+        Hex { q, r }
+    }
+}
+
+// A hexagon on a pointy-top hex grid in axial coordinates. Going east increases q, going
+// south-east increases r
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl Hex {
+    pub fn new(q: i32, r: i32) -> Self {
+        Hex { q, r }
+    }
+
+    pub fn neighbor(self, direction: Direction) -> Self {
+        self + direction.offset()
+    }
+
+    pub fn neighbors(self) -> impl Iterator<Item = Hex> {
+        Direction::ALL.iter().map(move |&d| self.neighbor(d))
+    }
+
+    // The number of steps between two hexagons
+    pub fn distance(self, other: Hex) -> u32 {
+        let dq = self.q - other.q;
+        let dr = self.r - other.r;
+
+        (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex {
+            q: self.q + other.q,
+            r: self.r + other.r,
+        }
+    }
+}
+
+// Parses a path of undelimited directions like "esenee", starting at the origin, into the
+// hexagon it ends up at
+impl FromStr for Hex {
+    type Err = ParseHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut hex = Hex::default();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            let direction = match c {
+                'e' => Direction::East,
+                'w' => Direction::West,
+                'n' | 's' => match (c, chars.next()) {
+                    ('n', Some('e')) => Direction::NorthEast,
+                    ('n', Some('w')) => Direction::NorthWest,
+                    ('s', Some('e')) => Direction::SouthEast,
+                    ('s', Some('w')) => Direction::SouthWest,
+                    _ => return Err(ParseHexError::Direction),
+                },
+                _ => return Err(ParseHexError::Direction),
+            };
+            hex = hex.neighbor(direction);
+        }
+
+        Ok(hex)
+    }
+}
+
+// The lobby floor, where only the black tiles are stored
+struct Floor {
+    black: HashSet<Hex>,
+}
+
+impl Floor {
+    fn new<'a, I, S>(lines: I) -> Result<Self, ParseHexError>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        let mut black = HashSet::new();
+
+        for line in lines
+            .into_iter()
+            .map(|l| l.as_ref())
+            .filter(|l| !l.is_empty())
+        {
+            let hex = line.parse()?;
+            // Flipping a tile twice turns it back to white
+            if !black.remove(&hex) {
+                black.insert(hex);
+            }
+        }
+
+        Ok(Floor { black })
+    }
+
+    fn tick(&mut self) {
+        let mut black_neighbors: HashMap<Hex, usize> = HashMap::new();
+        for neighbor in self.black.iter().flat_map(|h| h.neighbors()) {
+            *black_neighbors.entry(neighbor).or_default() += 1;
+        }
+
+        // Only tiles next to a black tile can be black afterwards, a black tile without any black
+        // neighbors is flipped to white
+        self.black = black_neighbors
+            .into_iter()
+            .filter(|(hex, count)| {
+                if self.black.contains(hex) {
+                    *count == 1 || *count == 2
+                } else {
+                    *count == 2
+                }
+            })
+            .map(|(hex, _)| hex)
+            .collect();
+    }
+
+    fn black_count(&self) -> usize {
+        self.black.len()
+    }
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<usize, ParseHexError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    Ok(Floor::new(lines)?.black_count())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> Result<usize, ParseHexError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let mut floor = Floor::new(lines)?;
+
+    for _ in 0..100 {
+        floor.tick();
+    }
+
+    Ok(floor.black_count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &[
+        "sesenwnenenewseeswwswswwnenewsewsw",
+        "neeenesenwnwwswnenewnwwsewnenwseswesw",
+        "seswneswswsenwwnwse",
+        "nwnwneseeswswnenewneswwnewseswneseene",
+        "swweswneswnenwsewnwneneseenw",
+        "eesenwseswswnenwswnwnwsewwnwsene",
+        "sewnenenenesenwsewnenwwwse",
+        "wenwwweseeeweswwwnwwe",
+        "wsweesenenewnwwnwsenewsenwwsesesenwne",
+        "neeswseenwwswnwswswnw",
+        "nenwswwsewswnenenewsenwsenwnesesenew",
+        "enewnwewneswsewnwswenweswnenwsenwsw",
+        "sweneswneswneneenwnewenewwneswswnese",
+        "swwesenesewenwneswnwwneseswwne",
+        "enesenwswwswneneswsenwnewswseenwsese",
+        "wnwnesenesenenwwnenwsewesewsesesew",
+        "nenewswnwewswnenesenwnesewesw",
+        "eneswnwswnwsenenwnwnwwseeswneewsenese",
+        "neswnwewnwnwseenwseesewsenwsweewe",
+        "wseweeenwnesenwwwswnew",
+    ];
+
+    #[test]
+    fn part1_test() {
+        assert_eq!(part1(EXAMPLE).unwrap(), 10);
+    }
+
+    #[test]
+    fn part2_test() {
+        assert_eq!(part2(EXAMPLE).unwrap(), 2208);
+    }
+
+    #[test]
+    fn hex_test() {
+        assert_eq!("esew".parse::<Hex>().unwrap(), Hex::new(0, 1));
+        assert_eq!("nwwswee".parse::<Hex>().unwrap(), Hex::default());
+        assert!("enx".parse::<Hex>().is_err());
+
+        let hex = Hex::new(2, -1);
+        assert!(hex.neighbors().all(|n| n.distance(hex) == 1));
+        assert_eq!(Hex::new(-2, 3).distance(hex), 4);
+    }
+}
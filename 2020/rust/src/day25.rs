@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+const MODULUS: u64 = 20201227;
+const SUBJECT: u64 = 7;
+
+#[derive(Debug)]
+pub enum HandshakeError {
+    Format,
+    LoopSize,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Format => write!(f, "HandshakeError::Format"),
+            Self::LoopSize => write!(f, "HandshakeError::LoopSize"),
+        }
+    }
+}
+
+impl Error for HandshakeError {}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (a as u128 * b as u128 % modulus as u128) as u64
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Computes base^exponent mod modulus by repeated squaring
+pub fn mod_pow(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    base %= modulus;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+// Finds the smallest x with base^x = target mod modulus, using baby-step giant-step in
+// O(sqrt(modulus)) time and memory. Base and modulus have to be coprime, otherwise None is
+// returned
+pub fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    if modulus == 0 || gcd(base % modulus, modulus) != 1 {
+        return None;
+    }
+
+    let target = target % modulus;
+    if target == 1 % modulus {
+        return Some(0);
+    }
+
+    let m = (modulus as f64).sqrt().ceil() as u64;
+
+    // Baby steps: target * base^j for 0 <= j < m. Keeping the largest j for every value gives
+    // the smallest x below
+    let mut baby_steps = HashMap::with_capacity(m as usize);
+    let mut value = target;
+    for j in 0..m {
+        baby_steps.insert(value, j);
+        value = mul_mod(value, base, modulus);
+    }
+
+    // Giant steps: base^(i * m) for 1 <= i <= m. A match means base^(i * m - j) = target, which
+    // relies on base being invertible
+    let giant_step = mod_pow(base, m, modulus);
+    let mut value = 1;
+    for i in 1..=m {
+        value = mul_mod(value, giant_step, modulus);
+        if let Some(&j) = baby_steps.get(&value) {
+            return Some(i * m - j);
+        }
+    }
+
+    None
+}
+
+fn parse_keys<'a, I, S>(lines: I) -> Result<(u64, u64), HandshakeError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let keys = lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.parse::<u64>().map_err(|_| HandshakeError::Format))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match keys[..] {
+        [card, door] => Ok((card, door)),
+        _ => Err(HandshakeError::Format),
+    }
+}
+
+pub fn part1<'a, I, S>(lines: I) -> Result<u64, HandshakeError>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let (card, door) = parse_keys(lines)?;
+    let card_loop_size = discrete_log(SUBJECT, card, MODULUS).ok_or(HandshakeError::LoopSize)?;
+
+    Ok(mod_pow(door, card_loop_size, MODULUS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &["5764801", "17807724"];
+
+    #[test]
+    fn part1_test() {
+        assert_eq!(part1(EXAMPLE).unwrap(), 14897079);
+    }
+
+    #[test]
+    fn loop_size_test() {
+        assert_eq!(discrete_log(SUBJECT, 5764801, MODULUS), Some(8));
+        assert_eq!(discrete_log(SUBJECT, 17807724, MODULUS), Some(11));
+    }
+
+    #[test]
+    fn discrete_log_test() {
+        for modulus in [2, 3, 4, 9, 10, 13, 15, 16, 97, 101, 128, 221] {
+            for base in 0..modulus {
+                for target in 0..modulus {
+                    let brute_force = (0..modulus)
+                        .find(|&x| mod_pow(base, x, modulus) == target)
+                        .filter(|_| gcd(base, modulus) == 1);
+
+                    assert_eq!(
+                        discrete_log(base, target, modulus),
+                        brute_force,
+                        "{}^x = {} mod {}",
+                        base,
+                        target,
+                        modulus
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mod_pow_test() {
+        assert_eq!(mod_pow(7, 0, MODULUS), 1);
+        assert_eq!(mod_pow(7, 8, MODULUS), 5764801);
+        assert_eq!(mod_pow(3, 200, 1), 0);
+        assert_eq!(mod_pow(u64::MAX, 2, u64::MAX - 1), 1);
+    }
+}
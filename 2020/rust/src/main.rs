@@ -5,7 +5,8 @@ use std::io;
 
 use aoc2020::{
     day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
-    day14, day15, day16, day17, day18, day19, /*%IMPORT%*/ util,
+    day14, day15, day16, day17, day18, day19, day20, day21, day22, day23, day24, day25,
+    /*%IMPORT%*/ util,
 };
 
 build_const!("aoc2020");
@@ -140,39 +141,53 @@ fn main() -> io::Result<()> {
     if args.is_empty() || args.contains("16") {
         println!("Day 16");
         println!("{:?}", day16::part1(DAY16));
-        println!(
-            "{:?}",
-            day16::part2(DAY16).ok_or_else(|| util::to_ioerror(util::Error))?
-        );
+        println!("{:?}", day16::part2(DAY16));
     }
 
     if args.is_empty() || args.contains("17") {
         println!("Day 17");
-        println!(
-            "{:?}",
-            day17::part1(DAY17).ok_or_else(|| util::to_ioerror(util::Error))?
-        );
-        println!(
-            "{:?}",
-            day17::part2(DAY17).ok_or_else(|| util::to_ioerror(util::Error))?
-        );
+        println!("{:?}", day17::part1(DAY17).map_err(util::to_ioerror)?);
+        println!("{:?}", day17::part2(DAY17).map_err(util::to_ioerror)?);
     }
     if args.is_empty() || args.contains("18") {
         println!("Day 18");
-        println!(
-            "{:?}",
-            day18::part1(DAY18).ok_or_else(|| util::to_ioerror(util::Error))?
-        );
-        println!(
-            "{:?}",
-            day18::part2(DAY18).ok_or_else(|| util::to_ioerror(util::Error))?
-        );
+        println!("{:?}", day18::part1(DAY18).map_err(util::to_ioerror)?);
+        println!("{:?}", day18::part2(DAY18).map_err(util::to_ioerror)?);
     }
     if args.is_empty() || args.contains("19") {
         println!("Day 19");
         println!("{:?}", day19::part1(DAY19).map_err(util::to_ioerror)?);
         println!("{:?}", day19::part2(DAY19).map_err(util::to_ioerror)?);
     }
+    if args.is_empty() || args.contains("20") {
+        println!("Day 20");
+        println!("{:?}", day20::part1(DAY20));
+        println!("{:?}", day20::part2(DAY20));
+    }
+    if args.is_empty() || args.contains("21") {
+        println!("Day 21");
+        println!("{:?}", day21::part1(DAY21));
+        println!("{:?}", day21::part2(DAY21));
+    }
+    if args.is_empty() || args.contains("22") {
+        println!("Day 22");
+        println!("{:?}", day22::part1(DAY22));
+        println!("{:?}", day22::part2(DAY22));
+    }
+    if args.is_empty() || args.contains("23") {
+        println!("Day 23");
+        println!("{:?}", day23::part1(DAY23));
+        println!("{:?}", day23::part2(DAY23));
+    }
+    if args.is_empty() || args.contains("24") {
+        println!("Day 24");
+        println!("{:?}", day24::part1(DAY24));
+        println!("{:?}", day24::part2(DAY24));
+    }
+    if args.is_empty() || args.contains("25") {
+        println!("Day 25");
+        println!("{:?}", day25::part1(DAY25));
+    }
     /*%CALL%*/
 
     Ok(())
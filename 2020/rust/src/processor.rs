@@ -1,4 +1,4 @@
-use std::convert::TryFrom;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
 use std::num::ParseIntError;
@@ -39,6 +39,25 @@ pub enum Instruction {
     Nop(i32),
 }
 
+impl Instruction {
+    // The instruction with jmp and nop swapped, if it is one of those
+    pub fn swapped(self) -> Option<Instruction> {
+        match self {
+            Instruction::Acc(_) => None,
+            Instruction::Jmp(n) => Some(Instruction::Nop(n)),
+            Instruction::Nop(n) => Some(Instruction::Jmp(n)),
+        }
+    }
+
+    // The offset to the next instruction after executing this one
+    pub fn offset(self) -> i32 {
+        match self {
+            Instruction::Jmp(n) => n,
+            Instruction::Acc(_) | Instruction::Nop(_) => 1,
+        }
+    }
+}
+
 impl FromStr for Instruction {
     type Err = InstructionParseError<ParseIntError>;
 
@@ -57,49 +76,348 @@ impl FromStr for Instruction {
     }
 }
 
+// Writes the instruction in the same format it is parsed from
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Acc(n) => write!(f, "acc {:+}", n),
+            Instruction::Jmp(n) => write!(f, "jmp {:+}", n),
+            Instruction::Nop(n) => write!(f, "nop {:+}", n),
+        }
+    }
+}
+
+pub fn parse_program<'a, I, S>(
+    lines: I,
+) -> Result<Vec<Instruction>, InstructionParseError<ParseIntError>>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+        .map(Instruction::from_str)
+        .collect()
+}
+
+// Lists the program with addresses, and the target address of every jump
+pub fn disassemble(instructions: &[Instruction]) -> String {
+    let width = instructions.len().to_string().len();
+
+    instructions
+        .iter()
+        .enumerate()
+        .map(|(ip, ins)| match ins {
+            Instruction::Jmp(n) => {
+                format!("{:>w$}  {}  ; -> {}\n", ip, ins, ip as i32 + n, w = width)
+            }
+            _ => format!("{:>w$}  {}\n", ip, ins, w = width),
+        })
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StepResult {
+    // The next instruction can be executed
+    Running,
+    // The instruction pointer is right after the last instruction
+    Halted,
+    // The next instruction has been executed before, so the program never halts
+    LoopDetected,
+    // The instruction pointer jumped outside of the program
+    OutOfBounds,
+    // Execution stopped before an instruction with a breakpoint, only returned by `run`
+    Breakpoint,
+}
+
+// An executed instruction and the accumulator after executing it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub ip: usize,
+    pub instruction: Instruction,
+    pub acc: i32,
+}
+
 #[derive(Debug)]
 pub struct Processor<'a> {
     pub instructions: &'a [Instruction],
     pub acc: i32,
-    pub ip: usize,
+    pub ip: i32,
+    visited: Vec<bool>,
+    breakpoints: HashSet<usize>,
+    trace: Option<Vec<TraceEntry>>,
 }
 
 impl<'a> Processor<'a> {
-    pub fn new(instructions: &'a [Instruction]) -> Processor {
-        if instructions.is_empty() {}
-
+    pub fn new(instructions: &'a [Instruction]) -> Processor<'a> {
         Processor {
-            instructions: instructions,
+            instructions,
             acc: 0,
             ip: 0,
+            visited: vec![false; instructions.len()],
+            breakpoints: HashSet::new(),
+            trace: None,
         }
     }
 
+    // Clears the state of the previous run, breakpoints and tracing stay enabled
     pub fn reset(&mut self) {
         self.ip = 0;
         self.acc = 0;
+        self.visited.iter_mut().for_each(|v| *v = false);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+    }
+
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    // The executed instructions in order, empty unless tracing is enabled
+    pub fn trace(&self) -> &[TraceEntry] {
+        self.trace.as_deref().unwrap_or(&[])
+    }
+
+    pub fn add_breakpoint(&mut self, ip: usize) {
+        self.breakpoints.insert(ip);
+    }
+
+    pub fn remove_breakpoint(&mut self, ip: usize) {
+        self.breakpoints.remove(&ip);
     }
 
-    pub fn execute(&mut self) -> Result<Option<i32>, Box<dyn Error + Send + Sync>> {
-        if self.ip == self.instructions.len() {
-            Ok(Some(self.acc))
+    pub fn status(&self) -> StepResult {
+        let len = self.instructions.len() as i32;
+
+        if self.ip == len {
+            StepResult::Halted
+        } else if self.ip < 0 || self.ip > len {
+            StepResult::OutOfBounds
+        } else if self.visited[self.ip as usize] {
+            StepResult::LoopDetected
         } else {
-            Ok(self.instructions.get(self.ip).and_then(|ins| {
-                match *ins {
-                    Instruction::Acc(n) => {
-                        self.acc += n;
-                        self.ip += 1;
-                    }
-                    Instruction::Jmp(n) => {
-                        self.ip = usize::try_from((self.ip as i32) + n).ok()?;
-                    }
-                    Instruction::Nop(_n) => {
-                        self.ip += 1;
-                    }
-                }
-
-                None
-            }))
+            StepResult::Running
+        }
+    }
+
+    // Executes a single instruction if possible, and returns the status afterwards
+    pub fn step(&mut self) -> StepResult {
+        let status = self.status();
+        if status != StepResult::Running {
+            return status;
+        }
+
+        let ip = self.ip as usize;
+        let instruction = self.instructions[ip];
+        self.visited[ip] = true;
+
+        if let Instruction::Acc(n) = instruction {
+            self.acc += n;
         }
+        self.ip += instruction.offset();
+
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(TraceEntry {
+                ip,
+                instruction,
+                acc: self.acc,
+            });
+        }
+
+        self.status()
+    }
+
+    // Executes instructions until the program stops or a breakpoint is reached. The instruction
+    // the processor is at is always executed, so calling this again continues past a breakpoint
+    pub fn run(&mut self) -> StepResult {
+        loop {
+            let status = self.step();
+            if status != StepResult::Running {
+                return status;
+            }
+
+            if self.breakpoints.contains(&(self.ip as usize)) {
+                return StepResult::Breakpoint;
+            }
+        }
+    }
+}
+
+// A single changed instruction which makes a program halt
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Repair {
+    pub ip: usize,
+    pub original: Instruction,
+    pub replacement: Instruction,
+    pub acc: i32,
+}
+
+// The instructions from which the program halts, found by walking the control flow backwards
+// from right after the last instruction
+pub fn halting_set(instructions: &[Instruction]) -> Vec<bool> {
+    let len = instructions.len();
+    let mut predecessors = vec![vec![]; len + 1];
+
+    for (ip, instruction) in instructions.iter().enumerate() {
+        let target = ip as i32 + instruction.offset();
+        if (0..=len as i32).contains(&target) {
+            predecessors[target as usize].push(ip);
+        }
+    }
+
+    let mut halts = vec![false; len + 1];
+    let mut stack = vec![len];
+    halts[len] = true;
+
+    while let Some(ip) = stack.pop() {
+        for &predecessor in predecessors[ip].iter() {
+            if !halts[predecessor] {
+                halts[predecessor] = true;
+                stack.push(predecessor);
+            }
+        }
+    }
+
+    halts
+}
+
+// Finds a single instruction to replace with `patch` so that the program halts. Only
+// instructions the unchanged program executes matter, and of those, the first one whose
+// replacement continues at an instruction that halts is taken.
+//
+// If the unchanged program loops, the replacement is never executed again afterwards, because
+// then the original instruction would have been in the halting set too, so the first candidate
+// always works
+pub fn repair<F>(instructions: &[Instruction], patch: F) -> Option<Repair>
+where
+    F: Fn(Instruction) -> Option<Instruction>,
+{
+    let halts = halting_set(instructions);
+    let mut processor = Processor::new(instructions);
+    processor.enable_trace();
+    processor.run();
+
+    let mut patched = instructions.to_vec();
+
+    for entry in processor.trace() {
+        let (ip, original) = (entry.ip, entry.instruction);
+        let replacement = match patch(original) {
+            Some(replacement) => replacement,
+            None => continue,
+        };
+
+        let target = ip as i32 + replacement.offset();
+        if !(0..halts.len() as i32).contains(&target) || !halts[target as usize] {
+            continue;
+        }
+
+        // Only a program which already halts can get here with a replacement that loops
+        patched[ip] = replacement;
+        let mut repaired = Processor::new(&patched);
+        if repaired.run() == StepResult::Halted {
+            return Some(Repair {
+                ip,
+                original,
+                replacement,
+                acc: repaired.acc,
+            });
+        }
+        patched[ip] = original;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &[
+        "nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1", "jmp -4", "acc +6",
+    ];
+
+    #[test]
+    fn step_test() {
+        let instructions = parse_program(EXAMPLE).unwrap();
+        let mut processor = Processor::new(&instructions);
+
+        assert_eq!(processor.step(), StepResult::Running);
+        assert_eq!(processor.step(), StepResult::Running);
+        assert_eq!(processor.step(), StepResult::Running);
+        assert_eq!((processor.ip, processor.acc), (6, 1));
+        assert_eq!(processor.run(), StepResult::LoopDetected);
+        assert_eq!((processor.ip, processor.acc), (1, 5));
+        assert_eq!(processor.step(), StepResult::LoopDetected);
+
+        let out_of_bounds = [Instruction::Acc(1), Instruction::Jmp(-2)];
+        assert_eq!(
+            Processor::new(&out_of_bounds).run(),
+            StepResult::OutOfBounds
+        );
+        assert_eq!(Processor::new(&[]).run(), StepResult::Halted);
+    }
+
+    #[test]
+    fn trace_and_breakpoint_test() {
+        let instructions = parse_program(EXAMPLE).unwrap();
+        let mut processor = Processor::new(&instructions);
+        processor.enable_trace();
+        processor.add_breakpoint(4);
+
+        assert_eq!(processor.run(), StepResult::Breakpoint);
+        assert_eq!(processor.ip, 4);
+        assert_eq!(processor.run(), StepResult::LoopDetected);
+        assert_eq!(
+            processor.trace().iter().map(|t| t.ip).collect::<Vec<_>>(),
+            [0, 1, 2, 6, 7, 3, 4]
+        );
+        assert_eq!(processor.trace()[5].acc, 5);
+
+        processor.reset();
+        assert!(processor.trace().is_empty());
+        assert_eq!(processor.run(), StepResult::Breakpoint);
+    }
+
+    #[test]
+    fn disassemble_test() {
+        let instructions = parse_program(&EXAMPLE[..3]).unwrap();
+
+        assert_eq!(
+            disassemble(&instructions),
+            "0  nop +0\n1  acc +1\n2  jmp +4  ; -> 6\n"
+        );
+        assert!(EXAMPLE
+            .iter()
+            .all(|l| l.parse::<Instruction>().unwrap().to_string() == *l));
+    }
+
+    #[test]
+    fn repair_test() {
+        let instructions = parse_program(EXAMPLE).unwrap();
+
+        assert_eq!(
+            repair(&instructions, Instruction::swapped),
+            Some(Repair {
+                ip: 7,
+                original: Instruction::Jmp(-4),
+                replacement: Instruction::Nop(-4),
+                acc: 8
+            })
+        );
+        assert_eq!(repair(&instructions, |_| None), None);
+    }
+
+    #[test]
+    fn halting_set_test() {
+        let instructions = parse_program(EXAMPLE).unwrap();
+
+        assert_eq!(
+            halting_set(&instructions),
+            [false, false, false, false, false, false, false, false, true, true]
+        );
+        assert_eq!(halting_set(&[Instruction::Jmp(2)]), [false, true]);
     }
 }
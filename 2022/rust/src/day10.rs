@@ -1,42 +1,25 @@
 use anyhow::anyhow;
+use std::fmt;
 
-pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<i32>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut register: i32 = 1;
-    let mut cycle_count: i32 = 0;
-    const INTERESTING_CYCLES: &[i32] = &[20, 60, 100, 140, 180, 220];
+use crate::ocr;
 
-    let mut sum: i32 = 0;
+const SCREEN_WIDTH: usize = 40;
+const SCREEN_HEIGHT: usize = 6;
 
-    for line in lines.into_iter().map(|l| l.as_ref()) {
-        // Last line of output may be empty, let's get around that
-        if line.is_empty() {
-            continue;
-        }
+#[derive(Debug, Copy, Clone)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+}
 
+impl Instruction {
+    fn from_line(line: &str) -> anyhow::Result<Self> {
         let mut words = line.split(' ');
 
         let Some(opcode) = words.next() else {
             return Err(anyhow!("Line did not contain opcode"));
         };
 
-        let next_cycle_count = match opcode {
-            "addx" => cycle_count + 2,
-            "noop" => cycle_count + 1,
-            _ => return Err(anyhow!("Unknown opcode")),
-        };
-
-        for interesting_cycle in INTERESTING_CYCLES {
-            let current_cycle_range = cycle_count + 1..=next_cycle_count;
-            if current_cycle_range.contains(interesting_cycle) {
-                let num = interesting_cycle * register;
-                sum += num;
-            }
-        }
-
         match opcode {
             "addx" => {
                 let arg = words
@@ -45,24 +28,158 @@ where
                     .parse::<i32>()
                     .map_err(|_| anyhow!("Could not parse addx argument"))?;
 
-                register += arg;
+                Ok(Instruction::Addx(arg))
+            }
+            "noop" => Ok(Instruction::Noop),
+            _ => Err(anyhow!("Unknown opcode")),
+        }
+    }
+}
+
+fn parse_program<'a, I, S>(lines: I) -> anyhow::Result<Vec<Instruction>>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        // Last line of output may be empty, let's get around that
+        .filter(|line| !line.is_empty())
+        .map(Instruction::from_line)
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Cycle {
+    // 1-based, like in the puzzle description
+    number: usize,
+    // Value of the X register *during* the cycle
+    x: i32,
+}
+
+// Iterating over the CPU yields each cycle of the program, until it runs out of instructions
+struct Cpu<'a> {
+    program: &'a [Instruction],
+    ip: usize,
+    x: i32,
+    cycle: usize,
+    // addx takes two cycles to complete; the register is only updated after the second one
+    pending_addx: Option<i32>,
+}
+
+impl<'a> Cpu<'a> {
+    fn new(program: &'a [Instruction]) -> Self {
+        Self {
+            program,
+            ip: 0,
+            x: 1,
+            cycle: 0,
+            pending_addx: None,
+        }
+    }
+}
+
+impl Iterator for Cpu<'_> {
+    type Item = Cycle;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(arg) = self.pending_addx.take() {
+            self.cycle += 1;
+            let cycle = Cycle {
+                number: self.cycle,
+                x: self.x,
+            };
+            self.x += arg;
+            return Some(cycle);
+        }
+
+        let instruction = self.program.get(self.ip)?;
+        self.ip += 1;
+
+        if let Instruction::Addx(arg) = *instruction {
+            self.pending_addx = Some(arg);
+        }
+
+        self.cycle += 1;
+        Some(Cycle {
+            number: self.cycle,
+            x: self.x,
+        })
+    }
+}
+
+pub struct Screen {
+    pixels: Vec<Vec<bool>>,
+}
+
+impl Screen {
+    fn draw(cpu: Cpu) -> Self {
+        let mut pixels = vec![vec![false; SCREEN_WIDTH]; SCREEN_HEIGHT];
+
+        for cycle in cpu.take(SCREEN_WIDTH * SCREEN_HEIGHT) {
+            let position = cycle.number - 1;
+            let (row, column) = (position / SCREEN_WIDTH, position % SCREEN_WIDTH);
+
+            // The sprite is 3 pixels wide, and is centered on the X register
+            pixels[row][column] = (cycle.x - column as i32).abs() <= 1;
+        }
+
+        Self { pixels }
+    }
+
+    pub fn letters(&self) -> anyhow::Result<String> {
+        ocr::recognize(&self.pixels)
+    }
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.pixels.iter() {
+            for &lit in row {
+                write!(f, "{}", if lit { '#' } else { '.' })?;
             }
-            "noop" => {}
-            _ => return Err(anyhow!("Unknown opcode")),
+            writeln!(f)?;
         }
 
-        cycle_count = next_cycle_count
+        Ok(())
     }
+}
+
+pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<i32>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    const INTERESTING_CYCLES: &[usize] = &[20, 60, 100, 140, 180, 220];
+
+    let program = parse_program(lines)?;
+
+    let sum = Cpu::new(&program)
+        .filter(|cycle| INTERESTING_CYCLES.contains(&cycle.number))
+        .map(|cycle| cycle.number as i32 * cycle.x)
+        .sum();
 
     Ok(sum)
 }
 
-pub fn part2<'a, I, S>(_lines: I) -> Option<u32>
+// The image drawn on the CRT, for when the letters need to be checked by eye
+pub fn render<'a, I, S>(lines: I) -> anyhow::Result<Screen>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    todo!()
+    let program = parse_program(lines)?;
+
+    Ok(Screen::draw(Cpu::new(&program)))
+}
+
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<String>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    render(lines)?.letters()
 }
 
 #[cfg(test)]
@@ -96,9 +213,31 @@ mod tests {
     }
 
     #[test]
-    fn part2_test() {
-        let result = part2(EXAMPLE).unwrap();
+    fn cpu_test() {
+        let program = parse_program(&["noop", "addx 3", "addx -5"]).unwrap();
+        let xs = Cpu::new(&program).map(|cycle| cycle.x).collect::<Vec<_>>();
 
-        assert_eq!(result, 0);
+        assert_eq!(xs, vec![1, 1, 1, 4, 4]);
+    }
+
+    #[test]
+    fn render_test() {
+        let result = render(EXAMPLE).unwrap().to_string();
+
+        assert_eq!(
+            result,
+            "##..##..##..##..##..##..##..##..##..##..\n\
+             ###...###...###...###...###...###...###.\n\
+             ####....####....####....####....####....\n\
+             #####.....#####.....#####.....#####.....\n\
+             ######......######......######......####\n\
+             #######.......#######.......#######.....\n"
+        );
+    }
+
+    #[test]
+    fn part2_test() {
+        // The example doesn't draw letters
+        assert!(part2(EXAMPLE).is_err());
     }
 }
@@ -8,4 +8,5 @@ pub mod day07;
 pub mod day08;
 pub mod day09;
 pub mod day10;
+pub mod ocr;
 pub mod util;
@@ -97,10 +97,8 @@ fn main() -> io::Result<()> {
     if args.is_empty() || args.contains("10") {
         println!("Day 10");
         println!("{:?}", day10::part1(DAY10).map_err(util::to_ioerror)?);
-        println!(
-            "{:?}",
-            day10::part2(DAY10).ok_or_else(|| util::to_ioerror(util::Error::empty()))?
-        );
+        print!("{}", day10::render(DAY10).map_err(util::to_ioerror)?);
+        println!("{:?}", day10::part2(DAY10).map_err(util::to_ioerror)?);
     }
 
     Ok(())
@@ -0,0 +1,93 @@
+use anyhow::anyhow;
+
+pub const LETTER_WIDTH: usize = 4;
+pub const LETTER_HEIGHT: usize = 6;
+
+// Letters are separated by a single empty column
+const LETTER_STRIDE: usize = LETTER_WIDTH + 1;
+
+// The block-letter font used by the puzzles. Not every letter of the alphabet has shown up in a
+// puzzle, so not every letter is known
+const FONT: &[(char, [&str; LETTER_HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+fn glyph_matches(glyph: &[&str; LETTER_HEIGHT], image: &[Vec<bool>], x: usize) -> bool {
+    glyph.iter().zip(image).all(|(glyph_row, image_row)| {
+        glyph_row
+            .chars()
+            .enumerate()
+            .all(|(dx, c)| image_row.get(x + dx).copied().unwrap_or(false) == (c == '#'))
+    })
+}
+
+// Reads the letters off of an image made of rows of lit (true) and dark (false) pixels
+pub fn recognize(image: &[Vec<bool>]) -> anyhow::Result<String> {
+    if image.len() != LETTER_HEIGHT {
+        return Err(anyhow!(
+            "Image must be {} pixels high, but it is {}",
+            LETTER_HEIGHT,
+            image.len()
+        ));
+    }
+
+    let width = image.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    (0..width)
+        .step_by(LETTER_STRIDE)
+        .map(|x| {
+            FONT.iter()
+                .find(|(_, glyph)| glyph_matches(glyph, image, x))
+                .map(|&(letter, _)| letter)
+                .ok_or(anyhow!("Unknown letter at column {}", x))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_image(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter()
+            .map(|row| row.chars().map(|c| c == '#').collect())
+            .collect()
+    }
+
+    #[test]
+    fn recognize_test() {
+        let image = to_image(&[
+            "#..#.####.###..###..",
+            "#..#.#....#..#.#..#.",
+            "####.###..#..#.#..#.",
+            "#..#.#....###..###..",
+            "#..#.#....#.#..#....",
+            "#..#.####.#..#.#....",
+        ]);
+
+        assert_eq!(recognize(&image).unwrap(), "HERP");
+    }
+
+    #[test]
+    fn recognize_unknown_letter_test() {
+        let image = to_image(&["####", "####", "####", "####", "####", "####"]);
+
+        assert!(recognize(&image).is_err());
+    }
+}
@@ -1,150 +1,111 @@
 use anyhow::anyhow;
+use std::collections::HashMap;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-enum Spring {
+enum Condition {
+    Operational,
     Damaged,
     Unknown,
 }
 
-type Segment = Vec<Spring>;
+#[derive(Debug, Clone)]
+struct Record {
+    springs: Vec<Condition>,
+    groups: Vec<usize>,
+}
+
+// (spring index, group index, length of the Damaged run ending right before the spring index)
+type MemoKey = (usize, usize, usize);
 
-// Operational springs act as separators between spring segments containing only Damaged or Unknown
-// springs
-fn parse_line(line: &str) -> anyhow::Result<(Vec<Segment>, Vec<i32>)> {
-    let (segments_str, groups_str) = line
+fn parse_line(line: &str) -> anyhow::Result<Record> {
+    let (springs_str, groups_str) = line
         .split_once(' ')
         .ok_or(anyhow!("Couldn't split line on whitespace"))?;
 
-    let segments = segments_str
-        .split('.')
-        .filter(|l| !l.is_empty())
-        .map(|l| {
-            l.chars()
-                .map(|c| match c {
-                    '#' => Ok(Spring::Damaged),
-                    '?' => Ok(Spring::Unknown),
-                    unknown => Err(anyhow!("Unexpected character in input: '{}'", unknown)),
-                })
-                .collect::<Result<Segment, _>>()
+    let springs = springs_str
+        .chars()
+        .map(|c| match c {
+            '.' => Ok(Condition::Operational),
+            '#' => Ok(Condition::Damaged),
+            '?' => Ok(Condition::Unknown),
+            unknown => Err(anyhow!("Unexpected character in input: '{}'", unknown)),
         })
         .collect::<Result<Vec<_>, _>>()?;
 
     let groups = groups_str
         .split(',')
-        .map(|n| n.parse::<i32>())
+        .map(|n| n.parse::<usize>())
         .collect::<Result<Vec<_>, _>>()?;
 
-    Ok((segments, groups))
-}
-
-// We cannot leave Damaged springs behind in the current segment!
-fn skipped_damaged_springs(segment: &Segment, i: usize) -> bool {
-    let Some(range) = segment.get(0..i.saturating_sub(1)) else {
-        return false;
-    };
-
-    range.contains(&Spring::Damaged)
+    Ok(Record { springs, groups })
 }
 
-// Start from the chosen index and expand in both directions until segment bounds or an Unknown
-// spring is hit
-fn get_damaged_range_bounds(segment: &Segment, i: usize, group_size: i32) -> (i32, i32) {
-    let mut start = i as i32;
-    let mut end = i as i32 + (group_size - 1);
-    let mut reached_beginning = false;
-    let mut reached_end = false;
-    let mut dist = 1;
-
-    loop {
-        if reached_beginning && reached_end {
-            break;
-        }
-
-        if !reached_beginning {
-            start -= dist;
-            if start < 0 {
-                start = 0;
-                reached_beginning = true;
-            } else if segment[start as usize] == Spring::Unknown {
-                start += 1;
-                reached_beginning = true;
+impl Record {
+    // Repeats the springs `times` times with Unknown springs inbetween, and the groups `times`
+    // times
+    fn unfold(&self, times: usize) -> Self {
+        let mut springs = Vec::with_capacity((self.springs.len() + 1) * times);
+        for i in 0..times {
+            if i > 0 {
+                springs.push(Condition::Unknown);
             }
+            springs.extend_from_slice(&self.springs);
         }
 
-        if !reached_end {
-            end += dist;
-            if end >= segment.len() as i32 || segment[end as usize] == Spring::Unknown {
-                reached_end = true;
-            }
+        Record {
+            springs,
+            groups: self.groups.repeat(times),
         }
-
-        dist += 1;
     }
 
-    // +1 because we need to have Operational springs inbetween groups of Damaged ones
-    (start, end)
-}
-
-fn solve(segments: &Vec<Segment>, groups: &[i32]) -> anyhow::Result<i32> {
-    // We reached the end
-    if groups.is_empty() {
-        return if segments
-            .iter()
-            .any(|seg| seg.iter().any(|&s| s == Spring::Damaged))
-        {
-            // ...but there are still Damaged springs left!
-            Ok(0)
-        } else {
-            Ok(1)
-        };
+    fn count_arrangements(&self) -> u64 {
+        let mut memo = HashMap::new();
+        self.count_arrangements_from((0, 0, 0), &mut memo)
     }
 
-    let mut sum = 0;
-    let group_size = groups[0];
-    let mut stop = false;
+    fn count_arrangements_from(&self, key: MemoKey, memo: &mut HashMap<MemoKey, u64>) -> u64 {
+        let (i, group_i, run) = key;
+
+        let Some(&spring) = self.springs.get(i) else {
+            // We reached the end: either all groups are closed already, or the run we're in
+            // closes the last one
+            let groups_left = self.groups.len() - group_i;
+            return match (groups_left, run) {
+                (0, 0) => 1,
+                (1, run) if run == self.groups[group_i] => 1,
+                _ => 0,
+            };
+        };
 
-    for (segment_i, segment) in segments.iter().enumerate() {
-        if stop {
-            break;
+        if let Some(&count) = memo.get(&key) {
+            return count;
         }
 
-        // We cannot leave Damaged springs behind in previous segments!
-        stop = segment.iter().any(|&s| s == Spring::Damaged);
-
-        for i in 0..segment.len().saturating_sub((group_size - 1) as usize) {
-            if skipped_damaged_springs(segment, i) {
-                break;
-            }
+        let group_size = self.groups.get(group_i).copied();
+        let mut count = 0;
 
-            let (damaged_group_start, damaged_group_end) =
-                get_damaged_range_bounds(segment, i, group_size);
-            if damaged_group_end - damaged_group_start != group_size {
-                continue;
+        if spring != Condition::Operational {
+            // Extend the current run, as long as it still fits the current group
+            if group_size.is_some_and(|size| run < size) {
+                count += self.count_arrangements_from((i + 1, group_i, run + 1), memo);
             }
+        }
 
-            let mut next_segments = segments.clone();
-            for _ in 0..segment_i {
-                next_segments.remove(0);
+        if spring != Condition::Damaged {
+            if run == 0 {
+                count += self.count_arrangements_from((i + 1, group_i, 0), memo);
+            } else if group_size == Some(run) {
+                // An Operational spring closes the current group
+                count += self.count_arrangements_from((i + 1, group_i + 1, 0), memo);
             }
-
-            if damaged_group_end == next_segments[0].len() as i32 {
-                next_segments.remove(0);
-            } else {
-                let next_first_segment = next_segments
-                    .get_mut(0)
-                    .expect("There must always be a next first segment - the current one");
-                *next_first_segment =
-                    next_first_segment[(damaged_group_end + 1) as usize..].to_vec();
-            }
-
-            sum += solve(&next_segments, &groups[1..])?;
         }
-    }
 
-    Ok(sum)
+        memo.insert(key, count);
+        count
+    }
 }
 
-pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<i32>
+pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u64>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
@@ -152,24 +113,185 @@ where
     let mut sum = 0;
 
     for line in lines.into_iter().map(|l| l.as_ref()) {
-        let (segments, groups) = parse_line(line)?;
-        sum += solve(&segments, &groups)?;
+        let record = parse_line(line)?;
+        sum += record.count_arrangements();
     }
 
     Ok(sum)
 }
 
-pub fn part2<'a, I, S>(_lines: I) -> anyhow::Result<i32>
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    todo!()
+    let mut sum = 0;
+
+    for line in lines.into_iter().map(|l| l.as_ref()) {
+        let record = parse_line(line)?.unfold(5);
+        sum += record.count_arrangements();
+    }
+
+    Ok(sum)
+}
+
+// The original segment-based solver. It is too slow for unfolded records, but it's kept around
+// to cross-check the memoized solver on small rows
+#[cfg(test)]
+mod reference {
+    use anyhow::anyhow;
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    enum Spring {
+        Damaged,
+        Unknown,
+    }
+
+    type Segment = Vec<Spring>;
+
+    // Operational springs act as separators between spring segments containing only Damaged or Unknown
+    // springs
+    fn parse_line(line: &str) -> anyhow::Result<(Vec<Segment>, Vec<i32>)> {
+        let (segments_str, groups_str) = line
+            .split_once(' ')
+            .ok_or(anyhow!("Couldn't split line on whitespace"))?;
+
+        let segments = segments_str
+            .split('.')
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                l.chars()
+                    .map(|c| match c {
+                        '#' => Ok(Spring::Damaged),
+                        '?' => Ok(Spring::Unknown),
+                        unknown => Err(anyhow!("Unexpected character in input: '{}'", unknown)),
+                    })
+                    .collect::<Result<Segment, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let groups = groups_str
+            .split(',')
+            .map(|n| n.parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((segments, groups))
+    }
+
+    // We cannot leave Damaged springs behind in the current segment!
+    fn skipped_damaged_springs(segment: &Segment, i: usize) -> bool {
+        let Some(range) = segment.get(0..i.saturating_sub(1)) else {
+            return false;
+        };
+
+        range.contains(&Spring::Damaged)
+    }
+
+    // Start from the chosen index and expand in both directions until segment bounds or an Unknown
+    // spring is hit
+    fn get_damaged_range_bounds(segment: &Segment, i: usize, group_size: i32) -> (i32, i32) {
+        let mut start = i as i32;
+        let mut end = i as i32 + (group_size - 1);
+        let mut reached_beginning = false;
+        let mut reached_end = false;
+        let mut dist = 1;
+
+        loop {
+            if reached_beginning && reached_end {
+                break;
+            }
+
+            if !reached_beginning {
+                start -= dist;
+                if start < 0 {
+                    start = 0;
+                    reached_beginning = true;
+                } else if segment[start as usize] == Spring::Unknown {
+                    start += 1;
+                    reached_beginning = true;
+                }
+            }
+
+            if !reached_end {
+                end += dist;
+                if end >= segment.len() as i32 || segment[end as usize] == Spring::Unknown {
+                    reached_end = true;
+                }
+            }
+
+            dist += 1;
+        }
+
+        // +1 because we need to have Operational springs inbetween groups of Damaged ones
+        (start, end)
+    }
+
+    fn solve(segments: &[Segment], groups: &[i32]) -> anyhow::Result<i32> {
+        // We reached the end
+        if groups.is_empty() {
+            return if segments.iter().any(|seg| seg.contains(&Spring::Damaged)) {
+                // ...but there are still Damaged springs left!
+                Ok(0)
+            } else {
+                Ok(1)
+            };
+        }
+
+        let mut sum = 0;
+        let group_size = groups[0];
+        let mut stop = false;
+
+        for (segment_i, segment) in segments.iter().enumerate() {
+            if stop {
+                break;
+            }
+
+            // We cannot leave Damaged springs behind in previous segments!
+            stop = segment.contains(&Spring::Damaged);
+
+            for i in 0..segment.len().saturating_sub((group_size - 1) as usize) {
+                if skipped_damaged_springs(segment, i) {
+                    break;
+                }
+
+                let (damaged_group_start, damaged_group_end) =
+                    get_damaged_range_bounds(segment, i, group_size);
+                if damaged_group_end - damaged_group_start != group_size {
+                    continue;
+                }
+
+                let mut next_segments = segments.to_vec();
+                for _ in 0..segment_i {
+                    next_segments.remove(0);
+                }
+
+                if damaged_group_end == next_segments[0].len() as i32 {
+                    next_segments.remove(0);
+                } else {
+                    let next_first_segment = next_segments
+                        .get_mut(0)
+                        .expect("There must always be a next first segment - the current one");
+                    *next_first_segment =
+                        next_first_segment[(damaged_group_end + 1) as usize..].to_vec();
+                }
+
+                sum += solve(&next_segments, &groups[1..])?;
+            }
+        }
+
+        Ok(sum)
+    }
+
+    pub fn count_arrangements(line: &str) -> anyhow::Result<i32> {
+        let (segments, groups) = parse_line(line)?;
+        solve(&segments, &groups)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::{collection::vec, prelude::*};
 
     static EXAMPLE: &[&str] = &[
         "???.### 1,1,3",
@@ -193,4 +315,26 @@ mod tests {
 
         assert_eq!(result, 525152);
     }
+
+    #[test]
+    fn unfold_test() {
+        let record = parse_line(".# 1").unwrap().unfold(5);
+        let expected = parse_line(".#?.#?.#?.#?.# 1,1,1,1,1").unwrap();
+
+        assert_eq!(record.springs, expected.springs);
+        assert_eq!(record.groups, expected.groups);
+    }
+
+    proptest! {
+        #[test]
+        fn matches_reference(springs in "[.#?]{1,14}", groups in vec(1..5usize, 1..5)) {
+            let groups = groups.iter().map(|g| g.to_string()).collect::<Vec<_>>().join(",");
+            let line = format!("{} {}", springs, groups);
+
+            let expected = reference::count_arrangements(&line).unwrap();
+            let result = parse_line(&line).unwrap().count_arrangements();
+
+            prop_assert_eq!(result, expected as u64);
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use anyhow::anyhow;
-use std::{fmt, hash::Hash};
+use std::{collections::HashMap, fmt, hash::Hash};
 
-#[derive(Clone, Copy, Hash)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
 enum Tile {
     Round,
     Cube,
@@ -37,7 +37,7 @@ impl fmt::Debug for Tile {
     }
 }
 
-#[derive(Default, Hash)]
+#[derive(Default, Clone, Hash, PartialEq, Eq)]
 struct Map {
     inner: Vec<Vec<Tile>>,
 }
@@ -58,8 +58,80 @@ impl Map {
 
             map.inner.push(row);
         }
+
+        // Tilting and transposing go by the size of the first row
+        if map.inner.first().is_none_or(|row| row.is_empty()) {
+            return Err(anyhow!("Found no tiles in input"));
+        }
+        if map.inner.iter().any(|row| row.len() != map.inner[0].len()) {
+            return Err(anyhow!("Found rows of different lengths in input"));
+        }
+
         Ok(map)
     }
+
+    fn tilt(&mut self, direction: TiltDirection) {
+        let height = self.inner.len();
+        let width = self.inner[0].len();
+
+        // Lines are walked in the direction the rocks are rolling *from*, so that each Round rock
+        // can be moved to the free position right after the last obstacle
+        let (line_count, line_len) = match direction {
+            TiltDirection::North | TiltDirection::South => (width, height),
+            TiltDirection::West | TiltDirection::East => (height, width),
+        };
+
+        for line in 0..line_count {
+            let position = |i: usize| -> (usize, usize) {
+                match direction {
+                    TiltDirection::North => (line, i),
+                    TiltDirection::South => (line, line_len - 1 - i),
+                    TiltDirection::West => (i, line),
+                    TiltDirection::East => (line_len - 1 - i, line),
+                }
+            };
+
+            let mut free = 0;
+            for i in 0..line_len {
+                let (x, y) = position(i);
+                match self.inner[y][x] {
+                    Tile::Cube => free = i + 1,
+                    Tile::Round => {
+                        let (free_x, free_y) = position(free);
+                        self.inner[y][x] = Tile::Empty;
+                        self.inner[free_y][free_x] = Tile::Round;
+                        free += 1;
+                    }
+                    Tile::Empty => {}
+                }
+            }
+        }
+    }
+
+    fn spin_cycle(&mut self) {
+        for direction in [
+            TiltDirection::North,
+            TiltDirection::West,
+            TiltDirection::South,
+            TiltDirection::East,
+        ] {
+            self.tilt(direction);
+        }
+    }
+
+    // Unlike `solve`, this doesn't tilt the map; the rocks are weighed where they are
+    fn north_load(&self) -> u32 {
+        let height = self.inner.len();
+
+        self.inner
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                let round_count = row.iter().filter(|&&t| t == Tile::Round).count();
+                (round_count * (height - y)) as u32
+            })
+            .sum()
+    }
 }
 
 impl fmt::Debug for Map {
@@ -131,8 +203,31 @@ fn solve(map: &Map) -> u32 {
 }
 
 fn solve_part2(map: &Map) -> u32 {
-    let transposed = transpose_map(map);
-    transposed.inner.iter().map(solve_line).sum()
+    const SPIN_CYCLES: usize = 1_000_000_000;
+
+    let mut map = map.clone();
+    // The cycle count at which each map state was first seen
+    let mut seen: HashMap<Map, usize> = HashMap::new();
+    let mut cycles = 0;
+
+    while cycles < SPIN_CYCLES {
+        if let Some(&first_seen) = seen.get(&map) {
+            // The states repeat from here on, so we can skip ahead to the last period which fits
+            let period = cycles - first_seen;
+            let remaining = (SPIN_CYCLES - cycles) % period;
+
+            for _ in 0..remaining {
+                map.spin_cycle();
+            }
+            return map.north_load();
+        }
+
+        seen.insert(map.clone(), cycles);
+        map.spin_cycle();
+        cycles += 1;
+    }
+
+    map.north_load()
 }
 
 pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
@@ -177,6 +272,47 @@ mod tests {
         assert_eq!(result, 136);
     }
 
+    #[test]
+    fn spin_cycle_test() {
+        let mut map = Map::from_lines(EXAMPLE).unwrap();
+        map.spin_cycle();
+
+        let expected = Map::from_lines(&[
+            ".....#....",
+            "....#...O#",
+            "...OO##...",
+            ".OO#......",
+            ".....OOO#.",
+            ".O#...O#.#",
+            "....O#....",
+            "......OOOO",
+            "#...O###..",
+            "#..OO#....",
+        ])
+        .unwrap();
+
+        assert_eq!(map, expected);
+    }
+
+    #[test]
+    fn tilt_north_load_test() {
+        let mut map = Map::from_lines(EXAMPLE).unwrap();
+        map.tilt(TiltDirection::North);
+
+        assert_eq!(map.north_load(), 136);
+    }
+
+    #[test]
+    fn from_lines_test() {
+        assert!(Map::from_lines(EXAMPLE).is_ok());
+
+        let empty: &[&str] = &[];
+        assert!(part1(empty).is_err());
+        assert!(part2(empty).is_err());
+        assert!(part2(&[""]).is_err());
+        assert!(part2(&["O.", "#"]).is_err());
+    }
+
     #[test]
     fn part2_test() {
         let result = part2(EXAMPLE).unwrap();
@@ -1,122 +1,220 @@
-use std::{collections::BTreeSet, iter::Peekable};
+use anyhow::anyhow;
 
-#[derive(Debug, Clone)]
-enum Tile {
-    Space,
-    Obstruction,
-}
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-struct V2 {
-    x: i32,
-    y: i32,
-}
+// Directions in the order the guard turns through them
+const UP: usize = 0;
+const RIGHT: usize = 1;
+const DOWN: usize = 2;
+const LEFT: usize = 3;
 
-fn get_deltas_iter() -> Peekable<impl Iterator<Item = V2>> {
-    let deltas = vec![
-        V2 { x: 0, y: -1 },
-        V2 { x: 1, y: 0 },
-        V2 { x: 0, y: 1 },
-        V2 { x: -1, y: 0 },
-    ];
-    deltas.into_iter().cycle().peekable()
-}
+// Marks a jump that leaves the map
+const EXIT: u32 = u32::MAX;
 
 #[derive(Debug, Clone)]
 struct Map {
-    tiles: Vec<Vec<Tile>>,
-    width: i32,
-    height: i32,
+    obstructions: Vec<bool>,
+    width: usize,
+    height: usize,
 }
 
 impl Map {
-    fn get_tile(&self, pos: &V2) -> &Tile {
-        &self.tiles[pos.y as usize][pos.x as usize]
-    }
-
-    fn with_new_obstruction(&self, pos: &V2) -> Self {
-        let mut new_map = self.clone();
-        new_map.tiles[pos.y as usize][pos.x as usize] = Tile::Obstruction;
-
-        new_map
+    fn step(&self, pos: usize, dir: usize) -> Option<usize> {
+        let (x, y) = (pos % self.width, pos / self.width);
+
+        match dir {
+            UP if y > 0 => Some(pos - self.width),
+            RIGHT if x + 1 < self.width => Some(pos + 1),
+            DOWN if y + 1 < self.height => Some(pos + self.width),
+            LEFT if x > 0 => Some(pos - 1),
+            _ => None,
+        }
     }
 }
 
-fn parse<'a, I, S>(lines: I) -> (Map, V2)
+fn parse<'a, I, S>(lines: I) -> anyhow::Result<(Map, usize)>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut tiles = Vec::new();
+    let mut obstructions = Vec::new();
     let mut guard_position = None;
+    let mut width = None;
+
+    for line in lines
+        .into_iter()
+        .map(|s| s.as_ref())
+        .filter(|l| !l.is_empty())
+    {
+        if *width.get_or_insert(line.len()) != line.len() {
+            return Err(anyhow!("Rows have different widths"));
+        }
 
-    for (y, line) in lines.into_iter().map(|s| s.as_ref()).enumerate() {
-        let mut row = Vec::new();
-
-        for (x, c) in line.bytes().map(char::from).enumerate() {
+        for c in line.bytes().map(char::from) {
             let tile = match c {
-                '.' => Tile::Space,
-                '#' => Tile::Obstruction,
+                '.' => false,
+                '#' => true,
                 '^' => {
-                    guard_position = Some(V2 {
-                        x: x as i32,
-                        y: y as i32,
-                    });
-                    Tile::Space
+                    guard_position = Some(obstructions.len());
+                    false
                 }
-                _ => panic!("Found unrecognized tile type"),
+                _ => return Err(anyhow!("Found unrecognized tile type {}", c)),
             };
-            row.push(tile);
+            obstructions.push(tile);
         }
-
-        tiles.push(row);
     }
 
-    let height = tiles.len() as i32;
-    let width = tiles[0].len() as i32;
-
+    let width = width.ok_or_else(|| anyhow!("Map is empty"))?;
     let map = Map {
-        tiles,
+        height: obstructions.len() / width,
+        obstructions,
         width,
-        height,
     };
 
-    (map, guard_position.expect("Guard should exist"))
+    Ok((
+        map,
+        guard_position.ok_or_else(|| anyhow!("Guard should exist"))?,
+    ))
 }
 
-fn get_next_position(map: &Map, current_pos: &V2, delta: &V2) -> Option<V2> {
-    let pos = V2 {
-        x: current_pos.x + delta.x,
-        y: current_pos.y + delta.y,
-    };
+// For every position and direction, the position the guard stops at before the next obstruction,
+// or EXIT when the guard walks off the map
+struct JumpTable {
+    stops: Vec<u32>,
+}
+
+impl JumpTable {
+    fn new(map: &Map) -> Self {
+        let mut stops = vec![EXIT; map.obstructions.len() * 4];
+
+        // Sweep every row and column against each direction, remembering the last stop
+        for dir in [UP, RIGHT, DOWN, LEFT] {
+            let back = (dir + 2) % 4;
+            let starts: Vec<usize> = match dir {
+                UP => (0..map.width).collect(),
+                DOWN => (0..map.width)
+                    .map(|x| (map.height - 1) * map.width + x)
+                    .collect(),
+                LEFT => (0..map.height).map(|y| y * map.width).collect(),
+                _ => (0..map.height)
+                    .map(|y| y * map.width + map.width - 1)
+                    .collect(),
+            };
+
+            for start in starts {
+                let mut stop = EXIT;
+                let mut pos = Some(start);
+
+                while let Some(p) = pos {
+                    if map.obstructions[p] {
+                        stop = map.step(p, back).map_or(EXIT, |s| s as u32);
+                    } else {
+                        stops[p * 4 + dir] = stop;
+                    }
+                    pos = map.step(p, back);
+                }
+            }
+        }
+
+        JumpTable { stops }
+    }
 
-    let out_of_bounds = pos.x < 0 || pos.x >= map.width || pos.y < 0 || pos.y >= map.height;
-    if out_of_bounds {
-        return None;
+    // Where the guard stops with an extra obstruction at `block`
+    fn stop(&self, map: &Map, pos: usize, dir: usize, block: usize) -> u32 {
+        let stop = self.stops[pos * 4 + dir];
+
+        let (x, y) = (pos % map.width, pos / map.width);
+        let (bx, by) = (block % map.width, block / map.width);
+        let (sx, sy) = if stop == EXIT {
+            (usize::MAX, usize::MAX)
+        } else {
+            (stop as usize % map.width, stop as usize / map.width)
+        };
+
+        // Whether the block is between the guard and the stop, or anywhere ahead when exiting
+        let blocked = match dir {
+            UP => bx == x && by < y && (stop == EXIT || by >= sy),
+            DOWN => bx == x && by > y && (stop == EXIT || by <= sy),
+            LEFT => by == y && bx < x && (stop == EXIT || bx >= sx),
+            _ => by == y && bx > x && (stop == EXIT || bx <= sx),
+        };
+
+        if blocked {
+            map.step(block, (dir + 2) % 4).map_or(EXIT, |s| s as u32)
+        } else {
+            stop
+        }
     }
+}
 
-    Some(pos)
+// A set of (position, direction) states, which remembers what it has to clear
+struct Visited {
+    bits: Vec<u64>,
+    set: Vec<usize>,
 }
 
-fn get_visited_positions(map: &Map, mut guard_pos: V2) -> BTreeSet<V2> {
-    let mut deltas_iter = get_deltas_iter();
-    let mut current_delta = deltas_iter.next().unwrap();
+impl Visited {
+    fn new(map: &Map) -> Self {
+        Visited {
+            bits: vec![0; (map.obstructions.len() * 4).div_ceil(64)],
+            set: vec![],
+        }
+    }
 
-    let mut visited_positions = BTreeSet::from([guard_pos]);
+    fn insert(&mut self, pos: usize, dir: usize) -> bool {
+        let i = pos * 4 + dir;
+        let (word, bit) = (i / 64, 1 << (i % 64));
 
-    while let Some(pos) = get_next_position(map, &guard_pos, &current_delta) {
-        match map.get_tile(&pos) {
-            Tile::Obstruction => {
-                current_delta = deltas_iter.next().unwrap();
-            }
-            Tile::Space => {
-                guard_pos = pos;
-                visited_positions.insert(pos);
+        if self.bits[word] & bit != 0 {
+            return false;
+        }
+        if self.bits[word] == 0 {
+            self.set.push(word);
+        }
+        self.bits[word] |= bit;
+
+        true
+    }
+
+    fn clear(&mut self) {
+        for word in self.set.drain(..) {
+            self.bits[word] = 0;
+        }
+    }
+}
+
+// A position the guard reaches for the first time, and the state of the guard right before
+struct FirstVisit {
+    pos: usize,
+    from: usize,
+    dir: usize,
+}
+
+fn walk(map: &Map, mut guard_pos: usize) -> Vec<FirstVisit> {
+    let mut dir = UP;
+    let mut seen = vec![false; map.obstructions.len()];
+    let mut visits = vec![];
+
+    seen[guard_pos] = true;
+
+    while let Some(pos) = map.step(guard_pos, dir) {
+        if map.obstructions[pos] {
+            dir = (dir + 1) % 4;
+        } else {
+            if !seen[pos] {
+                seen[pos] = true;
+                visits.push(FirstVisit {
+                    pos,
+                    from: guard_pos,
+                    dir,
+                });
             }
+            guard_pos = pos;
         }
     }
 
-    visited_positions
+    visits
 }
 
 pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
@@ -124,34 +222,38 @@ where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let (map, guard_pos) = parse(lines);
-    Ok(get_visited_positions(&map, guard_pos).len() as u32)
+    let (map, guard_pos) = parse(lines)?;
+
+    // The starting position is visited too
+    Ok(walk(&map, guard_pos).len() as u32 + 1)
 }
 
-fn try_obstruction_candidate(map: Map, mut current_pos: V2) -> bool {
-    let mut deltas_iter = get_deltas_iter();
-    let mut current_delta = deltas_iter.next().unwrap();
+// Whether the guard ends up in a loop with an obstruction at the first visit. The path up to
+// there is the same as without the obstruction, so the guard starts right in front of it
+fn try_obstruction_candidate(
+    map: &Map,
+    jumps: &JumpTable,
+    visited: &mut Visited,
+    candidate: &FirstVisit,
+) -> bool {
+    let (mut pos, mut dir) = (candidate.from, candidate.dir);
+
+    let is_loop = loop {
+        let stop = jumps.stop(map, pos, dir, candidate.pos);
+        if stop == EXIT {
+            break false;
+        }
 
-    let mut visited_positions_with_deltas = BTreeSet::from([(current_pos, current_delta)]);
+        pos = stop as usize;
+        dir = (dir + 1) % 4;
 
-    while let Some(pos) = get_next_position(&map, &current_pos, &current_delta) {
-        match map.get_tile(&pos) {
-            Tile::Obstruction => {
-                current_delta = deltas_iter.next().unwrap();
-            }
-            Tile::Space => {
-                current_pos = pos;
-                let current_pos_with_delta = (current_pos, current_delta);
-                if visited_positions_with_deltas.contains(&current_pos_with_delta) {
-                    return true;
-                } else {
-                    visited_positions_with_deltas.insert(current_pos_with_delta);
-                }
-            }
+        if !visited.insert(pos, dir) {
+            break true;
         }
-    }
+    };
 
-    false
+    visited.clear();
+    is_loop
 }
 
 pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u32>
@@ -159,19 +261,30 @@ where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let (map, guard_pos) = parse(lines);
-    let mut visited_positions = get_visited_positions(&map, guard_pos);
-
-    visited_positions.remove(&guard_pos);
-
-    Ok(visited_positions
-        .iter()
-        .map(|vp| {
-            let new_map = map.with_new_obstruction(vp);
-            try_obstruction_candidate(new_map, guard_pos)
-        })
+    let (map, guard_pos) = parse(lines)?;
+    let jumps = JumpTable::new(&map);
+    let candidates = walk(&map, guard_pos);
+
+    #[cfg(feature = "rayon")]
+    let count = candidates
+        .par_iter()
+        .map_init(
+            || Visited::new(&map),
+            |visited, c| try_obstruction_candidate(&map, &jumps, visited, c),
+        )
         .filter(|&b| b)
-        .count() as u32)
+        .count();
+
+    #[cfg(not(feature = "rayon"))]
+    let count = {
+        let mut visited = Visited::new(&map);
+        candidates
+            .iter()
+            .filter(|c| try_obstruction_candidate(&map, &jumps, &mut visited, c))
+            .count()
+    };
+
+    Ok(count as u32)
 }
 
 #[cfg(test)]
@@ -1,9 +1,21 @@
+use std::env;
+
+use anyhow::Context;
 use day11::{read_input, solution};
 
 fn main() -> anyhow::Result<()> {
     let input = read_input()?;
 
     println!("Day 11");
+
+    // Counting for any other number of blinks instead of the two parts
+    if let Some(blinks) = env::args().nth(1) {
+        let blinks = blinks.parse().context("Blinks should be a number")?;
+        println!("{:?}", solution::solve(&input, blinks));
+
+        return Ok(());
+    }
+
     println!("{:?}", solution::part1(&input));
     println!("{:?}", solution::part2(&input));
 
@@ -1,80 +1,141 @@
-use std::collections::BTreeMap;
+use std::iter;
 
-use itertools::Itertools;
+use anyhow::{anyhow, Context};
+use rustc_hash::FxHashMap;
 
 type Int = u64;
 type Blinks = usize;
-type Cache = BTreeMap<Int, Vec<Int>>;
+// Every blink multiplies the number of stones by about 1.5, so even a u128 runs out a bit after
+// 200 blinks
+type Count = u128;
 
 fn get_digits_count(n: Int) -> Int {
     n.ilog10() as Int + 1
 }
 
-fn blink(n: Int) -> Vec<Int> {
+// The stone or two stones a stone turns into, or None if the engraved number gets too large
+fn blink(n: Int) -> Option<(Int, Option<Int>)> {
     if n == 0 {
-        return vec![1];
+        return Some((1, None));
     };
 
     let digits_count = get_digits_count(n);
-    if digits_count % 2 == 0 {
+    if digits_count.is_multiple_of(2) {
         let half_digits = digits_count / 2;
         let divisor = (10 as Int).pow(half_digits as u32);
 
         let last_two = n % divisor;
         let first_two = n / divisor;
 
-        return vec![first_two, last_two];
+        return Some((first_two, Some(last_two)));
     }
 
-    vec![n * 2024]
+    n.checked_mul(2024).map(|n| (n, None))
 }
 
-fn solve<'a, I, S>(lines: I, blink_count: Blinks) -> usize
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut cache: Cache = BTreeMap::new();
+// The number of stones a single stone turns into after some blinks. Stones don't affect each
+// other, so the counts can be kept across inputs and blink counts
+#[derive(Debug, Default)]
+pub struct Memo {
+    // The stones every stone seen so far turns into on the next blink
+    blinks: FxHashMap<Int, (Int, Option<Int>)>,
+    counts: FxHashMap<(Int, Blinks), Count>,
+}
 
-    let mut stones: BTreeMap<Int, usize> = lines
-        .into_iter()
-        .map(|s| s.as_ref().split(' ').map(|w| w.parse::<Int>().unwrap()))
-        .next()
-        .unwrap()
-        .dedup_with_count()
-        .map(|(k, v)| (v, k))
-        .collect();
-
-    for _ in 0..blink_count {
-        let mut intermediate_results: BTreeMap<Int, usize> = BTreeMap::new();
-
-        for (num, amount) in stones.iter() {
-            let results = cache.entry(*num).or_insert(blink(*num));
-            for result in results.iter() {
-                *intermediate_results.entry(*result).or_default() += amount;
+impl Memo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn count(&mut self, stone: Int, blinks: Blinks) -> anyhow::Result<Count> {
+        if let Some(&count) = self.counts.get(&(stone, blinks)) {
+            return Ok(count);
+        }
+
+        let count = self.count_all(&[stone], blinks)?;
+        self.counts.insert((stone, blinks), count);
+
+        Ok(count)
+    }
+
+    // Counts the stones after each blink at once, keeping only how many there are of every
+    // number. Equal stones from different starting stones are only blinked at once
+    pub fn count_all(&mut self, stones: &[Int], blinks: Blinks) -> anyhow::Result<Count> {
+        let mut counts: FxHashMap<Int, Count> = FxHashMap::default();
+        for &stone in stones {
+            *counts.entry(stone).or_default() += 1;
+        }
+
+        for blink_number in 1..=blinks {
+            let mut next: FxHashMap<Int, Count> =
+                FxHashMap::with_capacity_and_hasher(counts.len(), Default::default());
+
+            for (stone, count) in counts {
+                let (first, second) = match self.blinks.get(&stone) {
+                    Some(&stones) => stones,
+                    None => {
+                        let stones =
+                            blink(stone).ok_or_else(|| anyhow!("Stone {} got too large", stone))?;
+                        self.blinks.insert(stone, stones);
+                        stones
+                    }
+                };
+
+                for stone in iter::once(first).chain(second) {
+                    let total = next.entry(stone).or_default();
+                    *total = total.checked_add(count).ok_or_else(|| {
+                        anyhow!("Too many stones to count after {} blinks", blink_number)
+                    })?;
+                }
             }
+
+            counts = next;
         }
 
-        stones = intermediate_results;
+        counts
+            .values()
+            .try_fold(0 as Count, |sum, &count| sum.checked_add(count))
+            .with_context(|| format!("Too many stones to count after {} blinks", blinks))
     }
+}
+
+fn parse_stones<'a, I, S>(lines: I) -> anyhow::Result<Vec<Int>>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    lines
+        .into_iter()
+        .map(|s| s.as_ref())
+        .find(|l| !l.is_empty())
+        .context("Input should contain the stones")?
+        .split_whitespace()
+        .map(|w| w.parse::<Int>().context("Stones should be numbers"))
+        .collect()
+}
 
-    stones.values().sum()
+pub fn solve<'a, I, S>(lines: I, blink_count: Blinks) -> anyhow::Result<Count>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    Memo::new().count_all(&parse_stones(lines)?, blink_count)
 }
 
-pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<usize>
+pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<Count>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    Ok(solve(lines, 25))
+    solve(lines, 25)
 }
 
-pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<usize>
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<Count>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    Ok(solve(lines, 75))
+    solve(lines, 75)
 }
 
 #[cfg(test)]
@@ -87,7 +148,13 @@ mod tests {
     fn blink_works() {
         let nums: Vec<Int> = vec![0, 1, 2, 99, 2024];
 
-        let result = nums.into_iter().flat_map(blink).collect::<Vec<_>>();
+        let result = nums
+            .into_iter()
+            .flat_map(|n| {
+                let (first, second) = blink(n).unwrap();
+                std::iter::once(first).chain(second)
+            })
+            .collect::<Vec<_>>();
 
         assert_eq!(result, vec![1, 2024, 4048, 9, 9, 20, 24]);
     }
@@ -98,4 +165,27 @@ mod tests {
 
         assert_eq!(result, 55312);
     }
+
+    #[test]
+    fn memo_test() {
+        let mut memo = Memo::new();
+
+        assert_eq!(memo.count(125, 6).unwrap(), 7);
+        assert_eq!(memo.count(17, 6).unwrap(), 15);
+        assert_eq!(memo.count(0, 0).unwrap(), 1);
+        assert_eq!(memo.count_all(&[125, 17], 6).unwrap(), 22);
+
+        assert_eq!(memo.count(0, 150).unwrap(), 945860666074062862472779122);
+    }
+
+    #[test]
+    fn many_blinks_test() {
+        // As many blinks as a u128 can count
+        let result = solve(EXAMPLE, 211).unwrap();
+        assert_eq!(result, 320575344243909476330737409371858905856);
+
+        // Far more stones than a u128 can count
+        assert!(solve(EXAMPLE, 212).is_err());
+        assert!(solve(EXAMPLE, 500).is_err());
+    }
 }
@@ -72,13 +72,69 @@ where
     }
 }
 
-fn get_plot(map: &mut Map, origin: V2) -> BTreeSet<V2> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Region {
+    area: usize,
+    perimeter: usize,
+    sides: usize,
+}
+
+impl Map {
+    // Out of bounds positions never match
+    fn matches_at(&self, pos: &V2, dx: i32, dy: i32, plant: char) -> bool {
+        let x = (pos.x as i32) + dx;
+        let y = (pos.y as i32) + dy;
+
+        if x < 0 || x >= (self.width as i32) || y < 0 || y >= (self.height as i32) {
+            return false;
+        }
+
+        self.grid[y as usize][x as usize] == plant
+    }
+
+    // Every corner of the region starts a new side, so counting corners counts sides
+    fn corner_count(&self, pos: &V2, plant: char) -> usize {
+        let diagonals = [(1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+        diagonals
+            .iter()
+            .filter(|&&(dx, dy)| {
+                let horizontal = self.matches_at(pos, dx, 0, plant);
+                let vertical = self.matches_at(pos, 0, dy, plant);
+                let diagonal = self.matches_at(pos, dx, dy, plant);
+
+                // Convex corner, or concave corner
+                (!horizontal && !vertical) || (horizontal && vertical && !diagonal)
+            })
+            .count()
+    }
+
+    fn fence_count(&self, pos: &V2, plant: char) -> usize {
+        let deltas = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+        deltas
+            .iter()
+            .filter(|&&(dx, dy)| !self.matches_at(pos, dx, dy, plant))
+            .count()
+    }
+}
+
+fn get_region(map: &mut Map, origin: V2) -> Region {
     let plant = map.get(&origin);
 
     let mut plot = BTreeSet::from([origin]);
     let mut to_check = BTreeSet::from([origin]);
+    let mut region = Region {
+        area: 0,
+        perimeter: 0,
+        sides: 0,
+    };
 
     while let Some(pos_to_check) = to_check.pop_first() {
+        region.area += 1;
+        region.perimeter += map.fence_count(&pos_to_check, plant);
+        region.sides += map.corner_count(&pos_to_check, plant);
+
         let matching_neighbors = pos_to_check
             .neighbors(map.width, map.height)
             .into_iter()
@@ -93,7 +149,17 @@ fn get_plot(map: &mut Map, origin: V2) -> BTreeSet<V2> {
         }
     }
 
-    plot
+    region
+}
+
+fn get_regions(mut map: Map) -> Vec<Region> {
+    let mut regions = Vec::new();
+
+    while let Some(origin) = map.remaining.pop_first() {
+        regions.push(get_region(&mut map, origin));
+    }
+
+    regions
 }
 
 pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
@@ -101,40 +167,14 @@ where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut map = parse(lines);
-
-    let mut count = 0;
-
-    while let Some(origin) = map.remaining.pop_first() {
-        let plant = map.get(&origin);
-        let plot = get_plot(&mut map, origin);
-
-        let area = plot.len();
-        let perimeter: usize = plot
-            .iter()
-            .map(|pos| {
-                let different_neighbors_count = pos
-                    .neighbors(map.width, map.height)
-                    .iter()
-                    .filter(|p| map.get(p) != plant)
-                    .count();
-                let boundary_count = &[
-                    pos.x == 0,
-                    pos.y == 0,
-                    pos.x == map.width - 1,
-                    pos.y == map.height - 1,
-                ]
-                .iter()
-                .filter(|&&b| b)
-                .count();
-                different_neighbors_count + boundary_count
-            })
-            .sum();
+    let map = parse(lines);
 
-        count += area * perimeter;
-    }
+    let price: usize = get_regions(map)
+        .iter()
+        .map(|region| region.area * region.perimeter)
+        .sum();
 
-    Ok(count as u32)
+    Ok(price as u32)
 }
 
 pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u32>
@@ -142,7 +182,14 @@ where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    todo!()
+    let map = parse(lines);
+
+    let price: usize = get_regions(map)
+        .iter()
+        .map(|region| region.area * region.sides)
+        .sum();
+
+    Ok(price as u32)
 }
 
 #[cfg(test)]
@@ -162,6 +209,10 @@ mod tests {
         "MMMISSJEEE",
     ];
 
+    static EXAMPLE_E_SHAPED: &[&str] = &["EEEEE", "EXXXX", "EEEEE", "EXXXX", "EEEEE"];
+
+    static EXAMPLE_AB: &[&str] = &["AAAAAA", "AAABBA", "AAABBA", "ABBAAA", "ABBAAA", "AAAAAA"];
+
     #[test]
     fn part1_test() {
         let result = part1(EXAMPLE).unwrap();
@@ -173,6 +224,57 @@ mod tests {
     fn part2_test() {
         let result = part2(EXAMPLE).unwrap();
 
-        assert_eq!(result, 0);
+        assert_eq!(result, 1206);
+    }
+
+    #[test]
+    fn part2_e_shaped_test() {
+        let result = part2(EXAMPLE_E_SHAPED).unwrap();
+
+        assert_eq!(result, 236);
+    }
+
+    #[test]
+    fn part2_ab_test() {
+        let result = part2(EXAMPLE_AB).unwrap();
+
+        assert_eq!(result, 368);
+    }
+
+    #[test]
+    fn get_regions_test() {
+        let map = parse(&["AAAA", "BBCD", "BBCC", "EEEC"]);
+        let regions = get_regions(map);
+
+        assert_eq!(
+            regions,
+            vec![
+                Region {
+                    area: 4,
+                    perimeter: 10,
+                    sides: 4
+                },
+                Region {
+                    area: 4,
+                    perimeter: 8,
+                    sides: 4
+                },
+                Region {
+                    area: 3,
+                    perimeter: 8,
+                    sides: 4
+                },
+                Region {
+                    area: 4,
+                    perimeter: 10,
+                    sides: 8
+                },
+                Region {
+                    area: 1,
+                    perimeter: 4,
+                    sides: 4
+                },
+            ]
+        );
     }
 }
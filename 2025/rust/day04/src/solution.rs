@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
     Empty,
@@ -48,30 +46,83 @@ where
     Ok(World { tiles, w, h })
 }
 
+const NEIGHBORS: &[(i32, i32)] = &[
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Paper with fewer neighbors than this can be reached by a forklift
+const THRESHOLD: usize = 4;
+
+fn get_neighbors(world: &World, x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> + '_ {
+    NEIGHBORS
+        .iter()
+        .map(move |(dx, dy)| (x + dx, y + dy))
+        .filter(|&(xx, yy)| 0 <= xx && xx < world.w && 0 <= yy && yy < world.h)
+}
+
 fn get_neighbors_count(world: &World, x: i32, y: i32) -> usize {
-    let dxdy = &[
-        (-1, -1),
-        (0, -1),
-        (1, -1),
-        (-1, 0),
-        (1, 0),
-        (-1, 1),
-        (0, 1),
-        (1, 1),
-    ];
+    get_neighbors(world, x, y)
+        .filter(|&(xx, yy)| world.get_tile(xx, yy) == Tile::Paper)
+        .count()
+}
+
+// Removes all reachable paper in rounds, and returns how many were removed in each round.
+//
+// Instead of rescanning the world every round, the neighbor counts are kept up to date as paper
+// is removed. Only the neighbors of removed paper can become reachable, and they are removed in
+// the next round, so every tile is looked at a bounded number of times
+fn get_removal_rounds(world: &World) -> Vec<u32> {
+    let index = |x: i32, y: i32| (y * world.w + x) as usize;
+
+    let mut counts = vec![0; (world.w * world.h) as usize];
+    // Paper which is removed, or is about to be in the current round
+    let mut removed = vec![false; counts.len()];
+    let mut round = vec![];
+
+    for y in 0..world.h {
+        for x in 0..world.w {
+            if world.get_tile(x, y) == Tile::Paper {
+                counts[index(x, y)] = get_neighbors_count(world, x, y);
+                if counts[index(x, y)] < THRESHOLD {
+                    removed[index(x, y)] = true;
+                    round.push((x, y));
+                }
+            }
+        }
+    }
+
+    let mut rounds = vec![];
 
-    dxdy.iter()
-        .filter(|(dx, dy)| {
-            let xx = x + dx;
-            let yy = y + dy;
+    while !round.is_empty() {
+        rounds.push(round.len() as u32);
 
-            if !(0 <= xx && xx < world.w && 0 <= yy && yy < world.h) {
-                return false;
+        let mut next_round = vec![];
+        for &(x, y) in round.iter() {
+            for (xx, yy) in get_neighbors(world, x, y) {
+                let i = index(xx, yy);
+                if world.get_tile(xx, yy) != Tile::Paper || removed[i] {
+                    continue;
+                }
+
+                counts[i] -= 1;
+                if counts[i] < THRESHOLD {
+                    removed[i] = true;
+                    next_round.push((xx, yy));
+                }
             }
+        }
 
-            world.get_tile(xx, yy) == Tile::Paper
-        })
-        .count()
+        round = next_round;
+    }
+
+    rounds
 }
 
 pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
@@ -85,7 +136,7 @@ where
     for y in 0..world.h {
         for x in 0..world.w {
             let is_paper = world.get_tile(x, y) == Tile::Paper;
-            if is_paper && get_neighbors_count(&world, x, y) < 4 {
+            if is_paper && get_neighbors_count(&world, x, y) < THRESHOLD {
                 count += 1;
             }
         }
@@ -94,45 +145,23 @@ where
     Ok(count)
 }
 
-pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u32>
+// The number of paper removed in each round, for following the removal along
+pub fn removal_rounds<'a, I, S>(lines: I) -> anyhow::Result<Vec<u32>>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut world = parse_world(lines)?;
-    let mut removed = 0;
-
-    loop {
-        let mut to_remove = HashSet::new();
-
-        // this seems really slow, since we iterate over empty tiles multiple times, but it's
-        // substantially faster than storing just the paper coordinates in a set
-        //
-        // I tried using both a 2d vec and a set, and using the vec only for the lookups.
-        // It's not as slow, but still way slower
-        for y in 0..world.h {
-            for x in 0..world.w {
-                let is_paper = world.get_tile(x, y) == Tile::Paper;
-                if is_paper && get_neighbors_count(&world, x, y) < 4 {
-                    to_remove.insert((x, y));
-                }
-            }
-        }
-
-        if to_remove.is_empty() {
-            break;
-        }
-
-        removed += to_remove.len();
-
-        for &(x, y) in to_remove.iter() {
-            world.tiles[y as usize][x as usize] = Tile::Empty;
-        }
+    let world = parse_world(lines)?;
 
-        to_remove.clear();
-    }
+    Ok(get_removal_rounds(&world))
+}
 
-    Ok(removed as u32)
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u32>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    Ok(removal_rounds(lines)?.into_iter().sum())
 }
 
 #[cfg(test)]
@@ -165,4 +194,11 @@ mod tests {
 
         assert_eq!(result, 43);
     }
+
+    #[test]
+    fn removal_rounds_test() {
+        let result = removal_rounds(EXAMPLE).unwrap();
+
+        assert_eq!(result, vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+    }
 }
@@ -1,4 +1,9 @@
-use std::{cell::RefCell, collections::BTreeMap};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use anyhow::anyhow;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct V3 {
@@ -8,161 +13,330 @@ struct V3 {
 }
 
 impl V3 {
-    fn dist(&self, other: &V3) -> u64 {
+    // Squared distances order the same as distances, and are exact
+    fn dist2(&self, other: &V3) -> u64 {
         let xd = self.x.abs_diff(other.x);
         let yd = self.y.abs_diff(other.y);
         let zd = self.z.abs_diff(other.z);
 
-        let d = ((xd * xd + yd * yd + zd * zd) as f64).sqrt();
-
-        // Hack to turn float into something that can be used as a key in a map
-        (d * 1000000f64).floor() as u64
+        xd * xd + yd * yd + zd * zd
     }
 }
 
-pub fn part1<'a, I, S>(lines: I, mut connections: u32) -> anyhow::Result<u64>
+fn parse_boxes<'a, I, S>(lines: I) -> anyhow::Result<Vec<V3>>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut points: Vec<V3> = vec![];
+    let mut boxes: Vec<V3> = vec![];
 
-    for line in lines.into_iter().map(|l| l.as_ref()) {
+    for line in lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+    {
         let mut words = line.split(",");
-        let x = words.next().expect("x to exist").parse::<u64>()?;
-        let y = words.next().expect("y to exist").parse::<u64>()?;
-        let z = words.next().expect("z to exist").parse::<u64>()?;
-
-        points.push(V3 { x, y, z });
+        let mut next = || -> anyhow::Result<u64> {
+            Ok(words
+                .next()
+                .ok_or_else(|| anyhow!("Missing coordinate in {}", line))?
+                .parse::<u64>()?)
+        };
+
+        boxes.push(V3 {
+            x: next()?,
+            y: next()?,
+            z: next()?,
+        });
     }
 
-    let mut ds: BTreeMap<u64, Vec<(&V3, &V3)>> = BTreeMap::new();
+    Ok(boxes)
+}
+
+// The junction boxes bucketed into cubic cells, sized so that there's about one box per cell
+struct Grid {
+    min: V3,
+    cell_size: u64,
+    dims: [i64; 3],
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
 
-    // TODO:
-    // This is VERY expensive (0.17s) but I can't come up with anything better...
-    // Can I discover the shortest path without having to walk all paths?
-    for (i, p) in points.iter().enumerate() {
-        for pp in points.iter().skip(i + 1) {
-            let d = p.dist(pp);
-            ds.entry(d).or_default().push((p, pp));
+impl Grid {
+    fn new(boxes: &[V3]) -> Self {
+        let min = V3 {
+            x: boxes.iter().map(|b| b.x).min().unwrap_or(0),
+            y: boxes.iter().map(|b| b.y).min().unwrap_or(0),
+            z: boxes.iter().map(|b| b.z).min().unwrap_or(0),
+        };
+        let max = V3 {
+            x: boxes.iter().map(|b| b.x).max().unwrap_or(0),
+            y: boxes.iter().map(|b| b.y).max().unwrap_or(0),
+            z: boxes.iter().map(|b| b.z).max().unwrap_or(0),
+        };
+
+        let volume =
+            (max.x - min.x + 1) as f64 * (max.y - min.y + 1) as f64 * (max.z - min.z + 1) as f64;
+        let cell_size = (volume / boxes.len().max(1) as f64).cbrt().ceil().max(1.0) as u64;
+        let dims = [
+            ((max.x - min.x) / cell_size) as i64 + 1,
+            ((max.y - min.y) / cell_size) as i64 + 1,
+            ((max.z - min.z) / cell_size) as i64 + 1,
+        ];
+
+        let mut grid = Grid {
+            min,
+            cell_size,
+            dims,
+            cells: HashMap::new(),
+        };
+        for (i, b) in boxes.iter().enumerate() {
+            grid.cells.entry(grid.cell_of(b)).or_default().push(i);
         }
+
+        grid
     }
 
-    // This barely makes a difference in the bench as opposed to Vec<Vec<&V3>>
-    let mut graphs: Vec<RefCell<Vec<&V3>>> = vec![];
+    fn cell_of(&self, b: &V3) -> [i64; 3] {
+        [
+            ((b.x - self.min.x) / self.cell_size) as i64,
+            ((b.y - self.min.y) / self.cell_size) as i64,
+            ((b.z - self.min.z) / self.cell_size) as i64,
+        ]
+    }
 
-    for vs in ds.values() {
-        for &(v1, v2) in vs.iter() {
-            if connections == 0 {
-                break;
-            }
+    // The boxes in all cells at exactly Chebyshev distance `r` from `center`
+    fn ring(&self, center: [i64; 3], r: i64) -> impl Iterator<Item = usize> + '_ {
+        (-r..=r)
+            .flat_map(move |dx| (-r..=r).map(move |dy| (dx, dy)))
+            .flat_map(move |(dx, dy)| {
+                // Only the outer faces of the cube, unless we're on an outer face already
+                let dzs: Vec<i64> = if dx.abs() == r || dy.abs() == r {
+                    (-r..=r).collect()
+                } else if r == 0 {
+                    vec![0]
+                } else {
+                    vec![-r, r]
+                };
+                dzs.into_iter()
+                    .map(move |dz| [center[0] + dx, center[1] + dy, center[2] + dz])
+            })
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
 
-            connections -= 1;
+    // The ring after which every cell in the grid has been visited
+    fn last_ring(&self, center: [i64; 3]) -> i64 {
+        (0..3)
+            .map(|axis| center[axis].max(self.dims[axis] - 1 - center[axis]))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// The boxes with a higher index than `from`, in order of increasing distance. Rings of cells are
+// scanned outwards, and a candidate is only given out once no unscanned cell can hold a closer box
+struct Neighbors {
+    from: usize,
+    center: [i64; 3],
+    scanned: i64,
+    last_ring: i64,
+    candidates: BinaryHeap<Reverse<(u64, usize)>>,
+}
 
-            let vec1found = graphs.iter().find(|g| g.borrow().contains(&v1));
-            let vec2found = graphs
-                .iter()
-                .enumerate()
-                .find(|(_, g)| g.borrow().contains(&v2));
+impl Neighbors {
+    fn new(boxes: &[V3], grid: &Grid, from: usize) -> Self {
+        let center = grid.cell_of(&boxes[from]);
 
-            if let Some(vec1found) = vec1found {
-                if let Some((v2i, vec2found)) = vec2found {
-                    if vec1found == vec2found {
-                        continue;
-                    }
+        Neighbors {
+            from,
+            center,
+            scanned: -1,
+            last_ring: grid.last_ring(center),
+            candidates: BinaryHeap::new(),
+        }
+    }
 
-                    vec1found
-                        .borrow_mut()
-                        .extend_from_slice(&vec2found.borrow());
+    fn next(&mut self, boxes: &[V3], grid: &Grid) -> Option<(u64, usize)> {
+        loop {
+            // Boxes in unscanned rings are at least `scanned` cells away in some direction
+            let safe = if self.scanned >= self.last_ring {
+                u64::MAX
+            } else {
+                let bound = self.scanned.max(0) as u64 * grid.cell_size;
+                bound * bound
+            };
 
-                    graphs.swap_remove(v2i);
-                } else {
-                    vec1found.borrow_mut().push(v2);
+            match self.candidates.peek() {
+                Some(&Reverse((d2, _))) if d2 <= safe => {
+                    return self.candidates.pop().map(|Reverse(c)| c);
+                }
+                None if self.scanned >= self.last_ring => return None,
+                _ => {}
+            }
+
+            self.scanned += 1;
+            let from = &boxes[self.from];
+            for i in grid.ring(self.center, self.scanned) {
+                if i > self.from {
+                    self.candidates.push(Reverse((from.dist2(&boxes[i]), i)));
                 }
-            } else if let Some((_, vec2found)) = vec2found {
-                vec2found.borrow_mut().push(v1);
-            } else {
-                graphs.push(RefCell::new(vec![v1, v2]));
             }
         }
     }
+}
 
-    let mut gl: Vec<_> = graphs.iter().map(|v| v.borrow().len() as u64).collect();
-    gl.sort();
+// All pairs of boxes in order of increasing distance, produced lazily by merging the nearest
+// neighbour streams of every box
+struct ClosestPairs<'a> {
+    boxes: &'a [V3],
+    grid: Grid,
+    neighbors: Vec<Neighbors>,
+    heap: BinaryHeap<Reverse<(u64, usize, usize)>>,
+}
 
-    Ok(gl.iter().rev().take(3).product())
+impl<'a> ClosestPairs<'a> {
+    fn new(boxes: &'a [V3]) -> Self {
+        let grid = Grid::new(boxes);
+        let mut neighbors: Vec<_> = (0..boxes.len())
+            .map(|i| Neighbors::new(boxes, &grid, i))
+            .collect();
+        let heap = neighbors
+            .iter_mut()
+            .filter_map(|n| n.next(boxes, &grid).map(|(d2, j)| Reverse((d2, n.from, j))))
+            .collect();
+
+        ClosestPairs {
+            boxes,
+            grid,
+            neighbors,
+            heap,
+        }
+    }
 }
 
-pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut points: Vec<V3> = vec![];
+impl Iterator for ClosestPairs<'_> {
+    type Item = (usize, usize);
 
-    for line in lines.into_iter().map(|l| l.as_ref()) {
-        let mut words = line.split(",");
-        let x = words.next().expect("x to exist").parse::<u64>()?;
-        let y = words.next().expect("y to exist").parse::<u64>()?;
-        let z = words.next().expect("z to exist").parse::<u64>()?;
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, i, j)) = self.heap.pop()?;
+
+        if let Some((d2, k)) = self.neighbors[i].next(self.boxes, &self.grid) {
+            self.heap.push(Reverse((d2, i, k)));
+        }
 
-        points.push(V3 { x, y, z });
+        Some((i, j))
     }
+}
 
-    let mut ds: BTreeMap<u64, Vec<(&V3, &V3)>> = BTreeMap::new();
+// Disjoint sets of boxes which are connected
+struct Circuits {
+    parent: Vec<usize>,
+    size: Vec<u64>,
+    count: usize,
+}
 
-    for (i, p) in points.iter().enumerate() {
-        for pp in points.iter().skip(i + 1) {
-            let d = p.dist(pp);
-            ds.entry(d).or_default().push((p, pp));
+impl Circuits {
+    fn new(n: usize) -> Self {
+        Circuits {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            count: n,
         }
     }
 
-    let mut graphs: Vec<Vec<&V3>> = vec![];
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
 
-    for vs in ds.values() {
-        for &(v1, v2) in vs.iter() {
-            let vec1found = graphs.iter().enumerate().find(|(_, g)| g.contains(&v1));
-            let vec2found = graphs.iter().enumerate().find(|(_, g)| g.contains(&v2));
+        i
+    }
 
-            if let Some((v1i, vec1found)) = vec1found {
-                if let Some((v2i, vec2found)) = vec2found {
-                    if vec1found == vec2found {
-                        continue;
-                    }
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
 
-                    let mut new_vec = vec1found.clone();
-                    new_vec.extend_from_slice(vec2found);
+        if self.size[a] < self.size[b] {
+            (a, b) = (b, a);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.count -= 1;
+    }
 
-                    let (mini, maxi) = if v1i < v2i { (v1i, v2i) } else { (v2i, v1i) };
-                    graphs.swap_remove(maxi);
-                    graphs.swap_remove(mini);
-                    graphs.push(new_vec);
-                } else {
-                    let mut new_vec = vec1found.clone();
-                    new_vec.push(v2);
+    fn sizes(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.parent.len())
+            .filter(|&i| self.parent[i] == i)
+            .map(|i| self.size[i])
+    }
+}
 
-                    graphs.swap_remove(v1i);
-                    graphs.push(new_vec);
-                }
-            } else if let Some((v2i, vec2found)) = vec2found {
-                let mut new_vec = vec2found.clone();
-                new_vec.push(v1);
+// Kruskal's algorithm, connecting the closest pair of boxes on every step, even if they are
+// already in the same circuit
+struct Kruskal<'a> {
+    pairs: ClosestPairs<'a>,
+    circuits: Circuits,
+}
 
-                graphs.swap_remove(v2i);
-                graphs.push(new_vec);
-            } else {
-                let new_vec = vec![v1, v2];
-                graphs.push(new_vec);
-            }
+impl<'a> Kruskal<'a> {
+    fn new(boxes: &'a [V3]) -> Self {
+        Kruskal {
+            pairs: ClosestPairs::new(boxes),
+            circuits: Circuits::new(boxes.len()),
+        }
+    }
 
-            if graphs.len() == 1 && graphs[0].len() == points.len() {
-                return Ok(v1.x * v2.x);
-            }
+    fn connect_next(&mut self) -> Option<(usize, usize)> {
+        let (i, j) = self.pairs.next()?;
+        self.circuits.union(i, j);
+
+        Some((i, j))
+    }
+}
+
+pub fn part1<'a, I, S>(lines: I, connections: u32) -> anyhow::Result<u64>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let boxes = parse_boxes(lines)?;
+    let mut kruskal = Kruskal::new(&boxes);
+
+    for _ in 0..connections {
+        if kruskal.connect_next().is_none() {
+            break;
         }
     }
 
-    panic!("No result")
+    let mut sizes: Vec<_> = kruskal.circuits.sizes().collect();
+    sizes.sort_unstable();
+
+    Ok(sizes.iter().rev().take(3).product())
+}
+
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let boxes = parse_boxes(lines)?;
+    let mut kruskal = Kruskal::new(&boxes);
+
+    while kruskal.circuits.count > 1 {
+        let (i, j) = kruskal
+            .connect_next()
+            .ok_or_else(|| anyhow!("Ran out of pairs"))?;
+
+        if kruskal.circuits.count == 1 {
+            return Ok(boxes[i].x * boxes[j].x);
+        }
+    }
+
+    Err(anyhow!("Boxes are connected without any connections"))
 }
 
 #[cfg(test)]
@@ -205,4 +379,26 @@ mod tests {
 
         assert_eq!(result, 25272);
     }
+
+    #[test]
+    fn closest_pairs_test() {
+        let boxes = parse_boxes(EXAMPLE).unwrap();
+        let pairs: Vec<_> = ClosestPairs::new(&boxes).collect();
+
+        let mut expected = vec![];
+        for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                expected.push((boxes[i].dist2(&boxes[j]), i, j));
+            }
+        }
+        expected.sort_unstable();
+
+        assert_eq!(
+            pairs,
+            expected
+                .into_iter()
+                .map(|(_, i, j)| (i, j))
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -6,6 +6,7 @@ use std::{
 
 use anyhow::Context;
 
+mod edges;
 pub mod solution;
 
 pub fn read_input() -> anyhow::Result<Vec<String>> {
@@ -1,49 +1,40 @@
+use anyhow::anyhow;
+
+use crate::edges;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct V2 {
-    x: i64,
-    y: i64,
+pub(crate) struct V2 {
+    pub(crate) x: i64,
+    pub(crate) y: i64,
 }
 
 impl V2 {
-    fn area(&self, other: &V2) -> u64 {
+    pub(crate) fn area(&self, other: &V2) -> u64 {
         (self.y.abs_diff(other.y) + 1) * (self.x.abs_diff(other.x) + 1)
     }
+}
 
-    fn rect_polyedges(&self, other: &V2) -> [PolyEdge; 4] {
-        let mut p1 = *self;
-        let mut p2 = *other;
-
-        if p1.x > p2.x {
-            std::mem::swap(&mut p1, &mut p2);
-        }
+fn parse_points<'a, I, S>(lines: I) -> anyhow::Result<Vec<V2>>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let mut points: Vec<V2> = vec![];
 
-        let p3 = V2 { x: p1.x, y: p2.y };
-        let p4 = V2 { x: p2.x, y: p1.y };
-
-        let p1_on_top = p1.y > p2.y;
-
-        [
-            PolyEdge::new(Edge::new(p1, p3), Dir::Right),
-            PolyEdge::new(
-                Edge::new(p3, p2),
-                // if p1_on_top { Dir::Down } else { Dir::Up },
-                if p1_on_top { Dir::Up } else { Dir::Down },
-            ),
-            PolyEdge::new(Edge::new(p2, p4), Dir::Left),
-            PolyEdge::new(
-                Edge::new(p4, p1),
-                // if p1_on_top { Dir::Up } else { Dir::Down },
-                if p1_on_top { Dir::Down } else { Dir::Up },
-            ),
-        ]
+    for line in lines
+        .into_iter()
+        .map(|l| l.as_ref())
+        .filter(|l| !l.is_empty())
+    {
+        let (x_str, y_str) = line
+            .split_once(",")
+            .ok_or_else(|| anyhow!("Missing comma in {}", line))?;
+        let x = x_str.parse::<i64>()?;
+        let y = y_str.parse::<i64>()?;
+        points.push(V2 { x, y })
     }
 
-    fn sub(&self, other: &V2) -> V2 {
-        V2 {
-            x: self.x - other.x,
-            y: self.y - other.y,
-        }
-    }
+    Ok(points)
 }
 
 pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u64>
@@ -51,14 +42,7 @@ where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut dots: Vec<V2> = vec![];
-
-    for line in lines.into_iter().map(|l| l.as_ref()) {
-        let (x_str, y_str) = line.split_once(",").expect("line to contain a comma");
-        let x = x_str.parse::<i64>()?;
-        let y = y_str.parse::<i64>()?;
-        dots.push(V2 { x, y })
-    }
+    let dots = parse_points(lines)?;
 
     let mut max = 0;
 
@@ -74,232 +58,124 @@ where
     Ok(max)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Edge {
-    p1: V2,
-    p2: V2,
+// One axis of the compressed grid. Every distinct coordinate gets a cell, and so does every
+// non-empty gap between two coordinates. Gaps without any tiles in them are left out, so two
+// polygon edges right next to each other are in adjacent cells as well
+struct Axis {
+    starts: Vec<i64>,
 }
 
-impl Edge {
-    fn new(p1: V2, p2: V2) -> Self {
-        Self { p1, p2 }
-    }
+impl Axis {
+    fn new(mut values: Vec<i64>) -> Self {
+        values.sort_unstable();
+        values.dedup();
 
-    fn contains(&self, p: &V2) -> bool {
-        let (p1, p2) = (self.p1, self.p2);
-        let is_x_aligned = p1.x == p2.x;
-
-        if is_x_aligned {
-            p.x == p1.x && p.y >= p1.y.min(p2.y) && p.y <= p1.y.max(p2.y)
-        } else {
-            p.y == p1.y && p.x >= p1.x.min(p2.x) && p.x <= p1.x.max(p2.x)
+        let mut starts = vec![];
+        for (i, &v) in values.iter().enumerate() {
+            starts.push(v);
+            if values.get(i + 1).is_some_and(|&next| next > v + 1) {
+                starts.push(v + 1);
+            }
         }
-    }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct PolyEdge {
-    inner: Edge,
-    inside_dir: Dir,
-}
-
-impl PolyEdge {
-    fn new(inner: Edge, inside_dir: Dir) -> Self {
-        Self { inner, inside_dir }
+        Axis { starts }
     }
 
-    fn intersects_inside(&self, other: &Edge) -> bool {
-        let Some(isect) = self.intersection(other) else {
-            return false;
-        };
-
-        if isect == self.inner.p1 || isect == self.inner.p2 {
-            return false;
-        }
-
-        match self.inside_dir {
-            Dir::Left => other.p1.x < isect.x || other.p2.x < isect.x,
-            Dir::Up => other.p1.y > isect.y || other.p2.y > isect.y,
-            Dir::Right => other.p1.x > isect.x || other.p2.x > isect.x,
-            Dir::Down => other.p1.y < isect.y || other.p2.y < isect.y,
-        }
+    // The cell of a coordinate which is in the input, shifted by one to leave room for a border
+    // of outside cells
+    fn index(&self, v: i64) -> usize {
+        self.starts.binary_search(&v).unwrap_or_else(|i| i - 1) + 1
     }
 
-    // TODO: check if this can be simplified since our lines are axis-aligned
-    fn intersection(&self, other: &Edge) -> Option<V2> {
-        let (p1, p2) = (self.inner.p1, self.inner.p2);
-        let (p3, p4) = (other.p1, other.p2);
-
-        let pxd = (p1.x - p2.x) * (p3.y - p4.y) - (p1.y - p2.y) * (p3.x - p4.x);
-        let pyd = (p1.x - p2.x) * (p3.y - p4.y) - (p1.y - p2.y) * (p3.x - p4.x);
-
-        if pxd == 0 || pyd == 0 {
-            return None;
-        }
-
-        let px = ((p1.x * p2.y - p1.y * p2.x) * (p3.x - p4.x)
-            - (p1.x - p2.x) * (p3.x * p4.y - p3.y * p4.x))
-            / pxd;
-        let py = ((p1.x * p2.y - p1.y * p2.x) * (p3.y - p4.y)
-            - (p1.y - p2.y) * (p3.x * p4.y - p3.y * p4.x))
-            / pyd;
-
-        let p = V2 { x: px, y: py };
-
-        if self.inner.contains(&p) && other.contains(&p) {
-            Some(p)
-        } else {
-            None
-        }
-    }
-
-    fn overlaps(&self, other: &PolyEdge) -> bool {
-        let p1 = self.inner.p1;
-        let p2 = self.inner.p2;
-
-        let p3 = other.inner.p1;
-        let p4 = other.inner.p2;
-
-        let is_self_x_aligned = p1.y == p2.y;
-        let is_other_x_aligned = p3.y == p4.y;
-
-        if is_self_x_aligned != is_other_x_aligned {
-            return false;
-        }
-
-        let (mut a, mut b, mut c, mut d) = if is_self_x_aligned {
-            if p1.y != p3.y {
-                return false;
-            }
-            (p1.x, p2.x, p3.x, p4.x)
-        } else {
-            if p1.x != p3.x {
-                return false;
-            }
-            (p1.y, p2.y, p3.y, p4.y)
-        };
-
-        if a > b {
-            std::mem::swap(&mut a, &mut b);
-        }
-        if c > d {
-            std::mem::swap(&mut c, &mut d);
-        }
-        if a > c {
-            std::mem::swap(&mut a, &mut c);
-            std::mem::swap(&mut b, &mut d);
-        }
-
-        let edges_share_point = a == c || b == d;
-        let non_trivial_overlap = b > c;
-
-        edges_share_point || non_trivial_overlap
+    // The number of cells including the border on both sides
+    fn len(&self) -> usize {
+        self.starts.len() + 2
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Dir {
-    Left,
-    Up,
-    Right,
-    Down,
+// The polygon rasterised onto the compressed grid, with a 2D prefix sum over the cells outside
+// of it, so any rectangle can be checked in constant time
+struct CompressedGrid {
+    xs: Axis,
+    ys: Axis,
+    prefix: Vec<u32>,
 }
 
-impl Dir {
-    fn turn_left(&self) -> Dir {
-        match self {
-            Dir::Left => Dir::Down,
-            Dir::Up => Dir::Left,
-            Dir::Right => Dir::Up,
-            Dir::Down => Dir::Right,
+impl CompressedGrid {
+    fn new(points: &[V2]) -> Self {
+        let xs = Axis::new(points.iter().map(|p| p.x).collect());
+        let ys = Axis::new(points.iter().map(|p| p.y).collect());
+        let (width, height) = (xs.len(), ys.len());
+
+        let mut boundary = vec![false; width * height];
+        for (p1, p2) in points
+            .iter()
+            .zip(points.iter().skip(1).chain(points.first()))
+        {
+            let (x1, x2) = (xs.index(p1.x), xs.index(p2.x));
+            let (y1, y2) = (ys.index(p1.y), ys.index(p2.y));
+
+            for y in y1.min(y2)..=y1.max(y2) {
+                for x in x1.min(x2)..=x1.max(x2) {
+                    boundary[y * width + x] = true;
+                }
+            }
         }
-    }
 
-    fn turn_right(&self) -> Dir {
-        match self {
-            Dir::Left => Dir::Up,
-            Dir::Up => Dir::Right,
-            Dir::Right => Dir::Down,
-            Dir::Down => Dir::Left,
+        // Flood fill from the border, everything that's not reached is inside or on the polygon
+        let mut outside = vec![false; width * height];
+        let mut stack = vec![0];
+        outside[0] = true;
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+
+            for n in neighbors.into_iter().flatten() {
+                if !outside[n] && !boundary[n] {
+                    outside[n] = true;
+                    stack.push(n);
+                }
+            }
         }
-    }
-}
-
-fn assign_edge_inside_directions(edges: Vec<Edge>) -> Vec<PolyEdge> {
-    let mut inside_dirs: Vec<Option<Dir>> = vec![None; edges.len()];
 
-    // leftmost edge must be vertical, with the inside direction to the right
-    let (leftmost_edge_i, leftmost_edge) = edges
-        .iter()
-        .enumerate()
-        .filter(|(_, e)| e.p1.x == e.p2.x)
-        .min_by_key(|(_, e)| e.p1.x)
-        .expect("at least one edge");
-
-    let mut current_edge = leftmost_edge;
-    let mut current_dir = Dir::Right;
-
-    inside_dirs[leftmost_edge_i] = Some(current_dir);
-
-    for (i, edge) in edges
-        .iter()
-        .enumerate()
-        .cycle()
-        .skip(leftmost_edge_i + 1)
-        .take(edges.len() - 1)
-    {
-        let (p1, p2, p3) = (current_edge.p1, current_edge.p2, edge.p2); // current_edge.p2 == edge.p1
-
-        let d1 = p2.sub(&p1);
-        let d2 = p3.sub(&p2);
-
-        let is_right_turn = d1.x * d2.y - d1.y * d2.x < 0;
-
-        if is_right_turn {
-            current_dir = current_dir.turn_right();
-        } else {
-            current_dir = current_dir.turn_left();
+        // prefix[y][x] is the number of outside cells above and to the left of (x, y)
+        let stride = width + 1;
+        let mut prefix = vec![0; stride * (height + 1)];
+        for y in 0..height {
+            for x in 0..width {
+                prefix[(y + 1) * stride + x + 1] = outside[y * width + x] as u32
+                    + prefix[y * stride + x + 1]
+                    + prefix[(y + 1) * stride + x]
+                    - prefix[y * stride + x];
+            }
         }
 
-        inside_dirs[i] = Some(current_dir);
-        current_edge = edge;
+        CompressedGrid { xs, ys, prefix }
     }
 
-    edges
-        .iter()
-        .zip(inside_dirs)
-        .map(|(e, dir)| PolyEdge {
-            inner: *e,
-            inside_dir: dir.expect("all edges to have assigned directions"),
-        })
-        .collect()
-}
+    fn is_inside(&self, p1: &V2, p2: &V2) -> bool {
+        let (x1, x2) = (self.xs.index(p1.x), self.xs.index(p2.x));
+        let (y1, y2) = (self.ys.index(p1.y), self.ys.index(p2.y));
+        let (left, right) = (x1.min(x2), x1.max(x2) + 1);
+        let (top, bottom) = (y1.min(y2), y1.max(y2) + 1);
+        let stride = self.xs.len() + 1;
 
-pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
-{
-    let mut points: Vec<V2> = vec![];
+        let outside = self.prefix[bottom * stride + right] + self.prefix[top * stride + left]
+            - self.prefix[top * stride + right]
+            - self.prefix[bottom * stride + left];
 
-    for line in lines.into_iter().map(|l| l.as_ref()) {
-        let (x_str, y_str) = line.split_once(",").expect("line to contain a comma");
-        let x = x_str.parse::<i64>()?;
-        let y = y_str.parse::<i64>()?;
-        points.push(V2 { x, y })
+        outside == 0
     }
+}
 
-    let mut edges: Vec<Edge> = vec![];
-
-    // collect edges
-    for (p1, p2) in points
-        .iter()
-        .zip(points.iter().skip(1).chain(std::iter::once(&points[0])))
-    {
-        edges.push(Edge { p1: *p1, p2: *p2 })
-    }
-    let edges = assign_edge_inside_directions(edges);
-
+fn largest_inside_rectangle(points: &[V2]) -> u64 {
+    let grid = CompressedGrid::new(points);
     let mut max_area = 0;
 
     for (i, p1) in points.iter().enumerate() {
@@ -310,40 +186,34 @@ where
                 continue;
             }
 
-            let rect_polyedges = p1.rect_polyedges(p2);
-
-            // first, let's check if there are any input edges which poke a hole in our rectangle
-            let is_cut = rect_polyedges
-                .iter()
-                .any(|rpe| edges.iter().any(|e| rpe.intersects_inside(&e.inner)));
-
-            if is_cut {
-                continue;
-            }
-
-            // then, check if our rectangle is outside of the polygon that's formed by the input edges
-            let is_outside = rect_polyedges.iter().any(|rpe| {
-                edges
-                    .iter()
-                    .any(|e| rpe.overlaps(e) && rpe.inside_dir != e.inside_dir)
-            });
-
-            if is_outside {
-                continue;
-            }
-
             let area = p1.area(p2);
-            if area > max_area {
+            if area > max_area && grid.is_inside(p1, p2) {
                 max_area = area;
             }
         }
     }
 
-    Ok(max_area)
+    max_area
+}
+
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let points = parse_points(lines)?;
+
+    if cfg!(feature = "edge-intersection") {
+        Ok(edges::largest_inside_rectangle(&points))
+    } else {
+        Ok(largest_inside_rectangle(&points))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{HashMap, HashSet};
+
     use super::*;
 
     static EXAMPLE: &[&str] = &["7,1", "11,1", "11,7", "9,7", "9,5", "2,5", "2,3", "7,3"];
@@ -361,4 +231,116 @@ mod tests {
 
         assert_eq!(result, 24);
     }
+
+    #[test]
+    fn part2_narrow_notch_test() {
+        // The notch has no tiles in it, so the whole bounding box is red or green
+        let lines = &[
+            "100,102", "100,96", "108,96", "108,102", "103,102", "103,100", "102,100", "102,102",
+        ];
+
+        let points = parse_points(lines).unwrap();
+
+        assert_eq!(largest_inside_rectangle(&points), 63);
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    // Grows a random blob of cells and traces its outline clockwise. Blobs with holes, or with
+    // cells that only touch at a corner, don't have a single outline and are rejected
+    fn random_outline(state: &mut u64, cells: usize) -> Option<Vec<(i64, i64)>> {
+        let mut blob = HashSet::from([(0i64, 0i64)]);
+        while blob.len() < cells {
+            let cell = *blob
+                .iter()
+                .nth(xorshift(state) as usize % blob.len())
+                .unwrap();
+            let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][xorshift(state) as usize % 4];
+            blob.insert((cell.0 + dx, cell.1 + dy));
+        }
+
+        let mut next = HashMap::new();
+        for &(x, y) in &blob {
+            let sides = [
+                ((0, -1), (x, y), (x + 1, y)),
+                ((1, 0), (x + 1, y), (x + 1, y + 1)),
+                ((0, 1), (x + 1, y + 1), (x, y + 1)),
+                ((-1, 0), (x, y + 1), (x, y)),
+            ];
+            for ((dx, dy), from, to) in sides {
+                if !blob.contains(&(x + dx, y + dy)) && next.insert(from, to).is_some() {
+                    return None;
+                }
+            }
+        }
+
+        let start = *next.keys().next()?;
+        let mut outline = vec![start];
+        let mut current = next[&start];
+        while current != start {
+            outline.push(current);
+            current = next[&current];
+        }
+        if outline.len() != next.len() {
+            return None;
+        }
+
+        // Only keep the corners
+        let len = outline.len();
+        Some(
+            (0..len)
+                .filter(|&i| {
+                    let (prev, p, next) = (
+                        outline[(i + len - 1) % len],
+                        outline[i],
+                        outline[(i + 1) % len],
+                    );
+                    (p.0 - prev.0, p.1 - prev.1) != (next.0 - p.0, next.1 - p.1)
+                })
+                .map(|i| outline[i])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn compressed_grid_matches_edge_intersection_test() {
+        let mut state = 0x2025_0009;
+        let mut checked = 0;
+
+        while checked < 200 {
+            let cells = 2 + xorshift(&mut state) as usize % 30;
+            let Some(outline) = random_outline(&mut state, cells) else {
+                continue;
+            };
+
+            // Spread the lattice out unevenly. There's always a tile between two edges, since the
+            // edge intersection solver doesn't see the tiles in between
+            let mut xs: Vec<i64> = vec![0];
+            let mut ys: Vec<i64> = vec![0];
+            for _ in 0..80 {
+                xs.push(xs[xs.len() - 1] + 2 + (xorshift(&mut state) % 4) as i64);
+                ys.push(ys[ys.len() - 1] + 2 + (xorshift(&mut state) % 4) as i64);
+            }
+            let points: Vec<V2> = outline
+                .iter()
+                .map(|&(x, y)| V2 {
+                    x: xs[(x + 40) as usize],
+                    y: ys[(y + 40) as usize],
+                })
+                .collect();
+
+            assert_eq!(
+                largest_inside_rectangle(&points),
+                edges::largest_inside_rectangle(&points),
+                "{:?}",
+                points
+            );
+            checked += 1;
+        }
+    }
 }
@@ -0,0 +1,459 @@
+// Exact solver for small integer linear programs of the form
+//
+//     minimize sum(x), subject to A * x = b, x >= 0, x integer
+//
+// where A has nonnegative coefficients. The system is brought to reduced row echelon form over the
+// rationals, which expresses every pivot variable in terms of the free variables. Only the free
+// variables are searched, within the bounds implied by the targets.
+
+use std::cmp::Ordering;
+use std::ops::{Mul, Sub};
+
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn lcm(a: i64, b: i64) -> i64 {
+    a / gcd(a, b) * b
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rational {
+    num: i64,
+    // Always positive
+    den: i64,
+}
+
+impl Rational {
+    fn new(num: i64, den: i64) -> Self {
+        assert!(den != 0, "denominator must not be zero");
+
+        let sign = if den < 0 { -1 } else { 1 };
+        let divisor = gcd(num, den).max(1);
+
+        Self {
+            num: sign * num / divisor,
+            den: sign * den / divisor,
+        }
+    }
+
+    fn integer(n: i64) -> Self {
+        Self { num: n, den: 1 }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    fn recip(&self) -> Self {
+        Self::new(self.den, self.num)
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.num * rhs.den - rhs.num * self.den, self.den * rhs.den)
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.num * rhs.num, self.den * rhs.den)
+    }
+}
+
+// A pivot variable expressed in terms of the free variables, scaled to integers:
+// `scale * x[var] = rhs - sum(coefficients[i] * free[i])`
+#[derive(Debug)]
+struct PivotRow {
+    scale: i64,
+    rhs: i64,
+    coefficients: Vec<i64>,
+}
+
+impl PivotRow {
+    // `scale * x[var]`, counting only the free variables that have been assigned so far
+    fn partial(&self, free_values: &[i64]) -> i64 {
+        self.rhs
+            - self
+                .coefficients
+                .iter()
+                .zip(free_values)
+                .map(|(c, v)| c * v)
+                .sum::<i64>()
+    }
+
+    fn value(&self, free_values: &[i64]) -> Option<i64> {
+        let dividend = self.partial(free_values);
+
+        (dividend >= 0 && dividend % self.scale == 0).then_some(dividend / self.scale)
+    }
+}
+
+// Reduces the augmented matrix in place, returning the pivot column of each nonzero row
+fn reduce(matrix: &mut [Vec<Rational>], var_count: usize) -> Vec<usize> {
+    let mut pivots = Vec::new();
+    let mut row = 0;
+
+    for col in 0..var_count {
+        let Some(pivot_row) = (row..matrix.len()).find(|&r| !matrix[r][col].is_zero()) else {
+            continue;
+        };
+        matrix.swap(row, pivot_row);
+
+        let factor = matrix[row][col].recip();
+        for value in matrix[row].iter_mut() {
+            *value = *value * factor;
+        }
+
+        let pivot = matrix[row].clone();
+        for (r, other) in matrix.iter_mut().enumerate() {
+            if r == row || other[col].is_zero() {
+                continue;
+            }
+
+            let factor = other[col];
+            for (value, &p) in other.iter_mut().zip(&pivot) {
+                *value = *value - factor * p;
+            }
+        }
+
+        pivots.push(col);
+        row += 1;
+
+        if row == matrix.len() {
+            break;
+        }
+    }
+
+    pivots
+}
+
+struct Search<'a> {
+    pivot_rows: &'a [PivotRow],
+    // The original coefficients of each free variable
+    free_columns: Vec<Vec<i64>>,
+    // The targets, minus the contributions of the free variables chosen so far
+    remaining: Vec<i64>,
+    free_values: Vec<i64>,
+    // Whether increasing the last free variable increases the total
+    last_increases_total: bool,
+    best: Option<i64>,
+}
+
+impl Search<'_> {
+    // No variable can exceed any (remaining) target it contributes to
+    fn upper_bound(&self, free: usize) -> i64 {
+        self.free_columns[free]
+            .iter()
+            .zip(&self.remaining)
+            .filter(|(&c, _)| c > 0)
+            .map(|(c, r)| r / c)
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn add_free_value(&mut self, free: usize, value: i64) {
+        for (r, c) in self.remaining.iter_mut().zip(&self.free_columns[free]) {
+            *r -= c * value;
+        }
+    }
+
+    // The total, if every pivot variable ends up being a nonnegative integer
+    fn total(&self, free_sum: i64) -> Option<i64> {
+        let mut total = free_sum;
+        for row in self.pivot_rows {
+            total += row.value(&self.free_values)?;
+        }
+        Some(total)
+    }
+
+    fn run(&mut self, free_sum: i64) {
+        // Pivot variables are nonnegative, so they can only make the total larger
+        if self.best.is_some_and(|best| free_sum >= best) {
+            return;
+        }
+
+        let depth = self.free_values.len();
+        if depth == self.free_columns.len() {
+            if let Some(total) = self.total(free_sum) {
+                self.best = Some(self.best.map_or(total, |best| best.min(total)));
+            }
+            return;
+        }
+
+        if depth + 1 == self.free_columns.len() {
+            self.run_last(free_sum);
+            return;
+        }
+
+        // A pivot variable that is already negative can't be brought back up by the remaining free
+        // variables if none of them have a negative coefficient in its row
+        let dead_end = self.pivot_rows.iter().any(|row| {
+            row.coefficients[depth..].iter().all(|&c| c >= 0) && row.partial(&self.free_values) < 0
+        });
+        if dead_end {
+            return;
+        }
+
+        let bound = self.upper_bound(depth);
+        for value in 0..=bound {
+            self.free_values.push(value);
+            self.add_free_value(depth, value);
+            self.run(free_sum + value);
+            self.add_free_value(depth, -value);
+            self.free_values.pop();
+        }
+    }
+
+    // With every other free variable fixed, each pivot row limits the last free variable to a
+    // range, and the total changes linearly with it. So instead of trying every value, the
+    // intersection of the ranges is walked from its better end, up to the first integer solution.
+    fn run_last(&mut self, free_sum: i64) {
+        let depth = self.free_values.len();
+        let mut low = 0;
+        let mut high = self.upper_bound(depth);
+
+        for row in self.pivot_rows {
+            let rest = row.partial(&self.free_values);
+            let c = row.coefficients[depth];
+
+            match c.cmp(&0) {
+                Ordering::Greater => high = high.min(rest.div_euclid(c)),
+                Ordering::Less => low = low.max(-(rest.div_euclid(-c))),
+                Ordering::Equal if rest < 0 => return,
+                Ordering::Equal => {}
+            }
+        }
+
+        if low > high {
+            return;
+        }
+
+        let values: Box<dyn Iterator<Item = i64>> = if self.last_increases_total {
+            Box::new(low..=high)
+        } else {
+            Box::new((low..=high).rev())
+        };
+
+        for value in values {
+            self.free_values.push(value);
+            let total = self.total(free_sum + value);
+            self.free_values.pop();
+
+            if let Some(total) = total {
+                self.best = Some(self.best.map_or(total, |best| best.min(total)));
+                return;
+            }
+        }
+    }
+}
+
+// Finds the smallest sum of nonnegative integers `x` for which `coefficients * x = targets`, where
+// `coefficients[i][j]` is the contribution of `x[j]` to `targets[i]`. Coefficients must be
+// nonnegative. Returns `None` if there is no solution.
+pub fn minimize_sum(coefficients: &[Vec<i64>], targets: &[i64]) -> Option<u64> {
+    assert_eq!(coefficients.len(), targets.len());
+    assert!(coefficients.iter().flatten().all(|&c| c >= 0));
+
+    let var_count = coefficients.first().map_or(0, |row| row.len());
+
+    let mut matrix = coefficients
+        .iter()
+        .zip(targets)
+        .map(|(row, &target)| {
+            row.iter()
+                .chain([&target])
+                .map(|&n| Rational::integer(n))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let pivots = reduce(&mut matrix, var_count);
+
+    // A row reading `0 = nonzero` means the system is inconsistent
+    if matrix[pivots.len()..]
+        .iter()
+        .any(|row| !row[var_count].is_zero())
+    {
+        return None;
+    }
+
+    // The last free variable is the cheapest to search, so it should be the one with the widest range
+    let mut free_vars = (0..var_count)
+        .filter(|var| !pivots.contains(var))
+        .collect::<Vec<_>>();
+    free_vars.sort_by_key(|&var| {
+        coefficients
+            .iter()
+            .zip(targets)
+            .filter(|(row, _)| row[var] > 0)
+            .map(|(row, target)| target / row[var])
+            .min()
+    });
+
+    let pivot_rows = matrix[..pivots.len()]
+        .iter()
+        .map(|row| {
+            let scale = row.iter().fold(1, |acc, r| lcm(acc, r.den));
+            let scaled = |r: Rational| r.num * (scale / r.den);
+
+            PivotRow {
+                scale,
+                rhs: scaled(row[var_count]),
+                coefficients: free_vars.iter().map(|&f| scaled(row[f])).collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The total is the sum of the free variables and the pivot variables, so increasing the last
+    // free variable changes it by `1 - sum(coefficient / scale)`
+    let last_increases_total = free_vars.len().checked_sub(1).is_none_or(|last| {
+        let scales = pivot_rows.iter().fold(1, |acc, row| lcm(acc, row.scale));
+        let pivots_change = pivot_rows
+            .iter()
+            .map(|row| row.coefficients[last] * (scales / row.scale))
+            .sum::<i64>();
+
+        scales >= pivots_change
+    });
+
+    let free_columns = free_vars
+        .iter()
+        .map(|&f| coefficients.iter().map(|row| row[f]).collect())
+        .collect();
+
+    let mut search = Search {
+        pivot_rows: &pivot_rows,
+        free_columns,
+        remaining: targets.to_vec(),
+        last_increases_total,
+        free_values: Vec::with_capacity(free_vars.len()),
+        best: None,
+    };
+    search.run(0);
+
+    search.best.map(|best| best as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tries every number of presses for every variable
+    fn brute_force(coefficients: &[Vec<i64>], targets: &[i64]) -> Option<u64> {
+        fn go(coefficients: &[Vec<i64>], remaining: &mut Vec<i64>, var: usize) -> Option<i64> {
+            let var_count = coefficients[0].len();
+            if var == var_count {
+                return remaining.iter().all(|&r| r == 0).then_some(0);
+            }
+
+            let mut best: Option<i64> = None;
+            let mut presses = 0;
+            loop {
+                if let Some(rest) = go(coefficients, remaining, var + 1) {
+                    let total = rest + presses;
+                    if best.is_none_or(|b| total < b) {
+                        best = Some(total);
+                    }
+                }
+
+                let fits = coefficients
+                    .iter()
+                    .zip(remaining.iter())
+                    .all(|(row, &r)| r >= row[var]);
+                if !fits || coefficients.iter().all(|row| row[var] == 0) {
+                    break;
+                }
+
+                for (row, r) in coefficients.iter().zip(remaining.iter_mut()) {
+                    *r -= row[var];
+                }
+                presses += 1;
+            }
+
+            for (row, r) in coefficients.iter().zip(remaining.iter_mut()) {
+                *r += row[var] * presses;
+            }
+
+            best
+        }
+
+        go(coefficients, &mut targets.to_vec(), 0).map(|best| best as u64)
+    }
+
+    #[test]
+    fn rational_test() {
+        assert_eq!(Rational::new(2, -4), Rational::new(-1, 2));
+        assert_eq!(
+            Rational::new(1, 2) - Rational::new(1, 3),
+            Rational::new(1, 6)
+        );
+        assert_eq!(Rational::new(2, 3).recip(), Rational::new(3, 2));
+    }
+
+    #[test]
+    fn minimize_sum_test() {
+        // x0 + x1 = 3, x1 + x2 = 5
+        let coefficients = vec![vec![1, 1, 0], vec![0, 1, 1]];
+
+        assert_eq!(minimize_sum(&coefficients, &[3, 5]), Some(5));
+    }
+
+    #[test]
+    fn minimize_sum_unsolvable_test() {
+        // x0 + x1 = 3, x0 + x1 = 4
+        let coefficients = vec![vec![1, 1], vec![1, 1]];
+
+        assert_eq!(minimize_sum(&coefficients, &[3, 4]), None);
+    }
+
+    #[test]
+    fn minimize_sum_non_integer_test() {
+        // 2 * x0 = 3 has a rational solution only
+        let coefficients = vec![vec![2]];
+
+        assert_eq!(minimize_sum(&coefficients, &[3]), None);
+    }
+
+    #[test]
+    fn minimize_sum_matches_brute_force() {
+        // Small deterministic pseudo-random systems
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |modulo: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % modulo) as i64
+        };
+
+        for _ in 0..200 {
+            let rows = 1 + next(4) as usize;
+            let vars = 1 + next(5) as usize;
+            let coefficients = (0..rows)
+                .map(|_| (0..vars).map(|_| next(2)).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let presses = (0..vars).map(|_| next(4)).collect::<Vec<_>>();
+            let targets = coefficients
+                .iter()
+                .map(|row| row.iter().zip(&presses).map(|(c, p)| c * p).sum())
+                .collect::<Vec<i64>>();
+
+            assert_eq!(
+                minimize_sum(&coefficients, &targets),
+                brute_force(&coefficients, &targets),
+                "coefficients: {:?}, targets: {:?}",
+                coefficients,
+                targets
+            );
+        }
+    }
+}
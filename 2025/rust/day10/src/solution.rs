@@ -1,5 +1,10 @@
 use std::collections::{BTreeSet, VecDeque};
-use std::fmt::Display;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::ilp;
 
 #[derive(Debug, Clone, Copy)]
 struct Lights(u16);
@@ -48,18 +53,8 @@ impl Display for Lights {
 struct Button(u16);
 
 impl Button {
-    fn parse(s: &str) -> Self {
-        Self(
-            s.split(",")
-                .map(|n| {
-                    2_i32.pow(
-                        n.parse::<u32>().expect(
-                            "button definition to contain only numbers separated by commas",
-                        ),
-                    ) as u16
-                })
-                .sum(),
-        )
+    fn from_counters(counters: &[usize]) -> Self {
+        Self(counters.iter().map(|&c| 2_i32.pow(c as u32) as u16).sum())
     }
 }
 
@@ -69,40 +64,104 @@ impl Display for Button {
     }
 }
 
-pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
+struct Machine<'a> {
+    lights: &'a str,
+    // The counters (and lights) each button is wired to
+    buttons: Vec<Vec<usize>>,
+    joltages: Vec<i64>,
+}
+
+fn parse_numbers<T>(s: &str) -> Vec<T>
 where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a,
+    T: FromStr,
+    T::Err: fmt::Debug,
 {
-    let mut sum = 0;
+    s.split(",")
+        .map(|n| n.parse::<T>().expect("numbers to be separated by commas"))
+        .collect()
+}
 
-    for line in lines.into_iter().map(|l| l.as_ref()) {
+impl<'a> Machine<'a> {
+    fn parse(line: &'a str) -> Self {
         let (lights, rest) = line.split_once("]").expect("input to contain ']'");
         let lights = &lights[1..]; // remove '['
-        let lights = Lights::parse(lights);
 
-        let (buttons, _) = rest.split_once("{").expect("input to contain '{'");
+        let (buttons, joltages) = rest.split_once("{").expect("input to contain '{'");
         let buttons = buttons
             .split("(")
             .skip(1)
             .map(|b| {
                 let (button, _) = b.split_once(")").expect("buttons to be enclosed in parens");
-                Button::parse(button)
+                parse_numbers(button)
             })
             .collect::<Vec<_>>();
 
+        let joltages = joltages
+            .strip_suffix("}")
+            .expect("joltages to be enclosed in braces");
+        let joltages = parse_numbers(joltages);
+
+        Self {
+            lights,
+            buttons,
+            joltages,
+        }
+    }
+
+    // Each counter is the sum of the presses of the buttons wired to it, so finding the fewest
+    // presses is an integer linear program with a row per counter and a column per button
+    fn fewest_joltage_presses(&self) -> Option<u64> {
+        let coefficients = (0..self.joltages.len())
+            .map(|counter| {
+                self.buttons
+                    .iter()
+                    .map(|b| b.contains(&counter) as i64)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        ilp::minimize_sum(&coefficients, &self.joltages)
+    }
+}
+
+pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + 'a,
+{
+    let mut sum = 0;
+
+    for line in lines.into_iter().map(|l| l.as_ref()) {
+        let machine = Machine::parse(line);
+        let lights = Lights::parse(machine.lights);
+        let buttons = machine
+            .buttons
+            .iter()
+            .map(|b| Button::from_counters(b))
+            .collect::<Vec<_>>();
+
         sum += lights.solve(&buttons);
     }
 
     Ok(sum)
 }
 
-pub fn part2<'a, I, S>(_lines: I) -> anyhow::Result<u32>
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    todo!()
+    let mut sum = 0;
+
+    for line in lines.into_iter().map(|l| l.as_ref()) {
+        let machine = Machine::parse(line);
+
+        sum += machine
+            .fewest_joltage_presses()
+            .ok_or_else(|| anyhow!("no button presses reach the joltages of '{}'", line))?;
+    }
+
+    Ok(sum)
 }
 
 #[cfg(test)]
@@ -126,6 +185,16 @@ mod tests {
     fn part2_test() {
         let result = part2(EXAMPLE).unwrap();
 
-        assert_eq!(result, 0);
+        assert_eq!(result, 33);
+    }
+
+    #[test]
+    fn fewest_joltage_presses_test() {
+        let presses = EXAMPLE
+            .iter()
+            .map(|line| Machine::parse(line).fewest_joltage_presses())
+            .collect::<Vec<_>>();
+
+        assert_eq!(presses, vec![Some(10), Some(12), Some(11)]);
     }
 }
@@ -1,116 +1,120 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 
-fn find_paths(routes: &HashMap<&str, Vec<&str>>, src: &str, dst: &str) -> u32 {
-    let mut sum = 0;
-    let mut queue = VecDeque::from([src]);
+use anyhow::anyhow;
 
-    while let Some(current) = queue.pop_front() {
-        let Some(to) = routes.get(current) else {
-            continue;
-        };
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Visit {
+    InProgress,
+    Done(u64),
+}
 
-        for &t in to.iter() {
-            if t == dst {
-                sum += 1;
-            } else {
-                queue.push_back(t);
-            }
+struct Devices<'a> {
+    routes: HashMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> Devices<'a> {
+    fn parse<I, S>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        let mut routes = HashMap::new();
+
+        for line in lines.into_iter().map(|l| l.as_ref()) {
+            let (from, to) = line.split_once(":").expect("line to contain ':'");
+            let to = to.split(" ").skip(1).collect::<Vec<_>>();
+
+            routes.insert(from, to);
         }
+
+        Self { routes }
     }
 
-    sum
-}
+    // Counts the paths from `src` to `dst`, remembering the count for every device along the way.
+    // The devices are supposed to form a DAG; running into a cycle is an error, as there would be
+    // infinitely many paths.
+    fn count_paths(&self, src: &'a str, dst: &'a str) -> anyhow::Result<u64> {
+        let mut visits = HashMap::new();
+        self.count_paths_from(src, dst, &mut visits)
+    }
 
-fn find_paths_short_circuit(
-    routes: &HashMap<&str, Vec<&str>>,
-    src: &str,
-    dst: &str,
-) -> Option<u32> {
-    let mut sum = 0;
-    let mut queue = VecDeque::from([src]);
-
-    while let Some(current) = queue.pop_front() {
-        let Some(to) = routes.get(current) else {
-            continue;
-        };
+    fn count_paths_from(
+        &self,
+        current: &'a str,
+        dst: &'a str,
+        visits: &mut HashMap<&'a str, Visit>,
+    ) -> anyhow::Result<u64> {
+        if current == dst {
+            return Ok(1);
+        }
 
-        for &t in to.iter() {
-            if t == dst {
-                sum += 1;
-            } else {
-                queue.push_back(t);
+        match visits.get(current) {
+            Some(Visit::Done(count)) => return Ok(*count),
+            Some(Visit::InProgress) => {
+                return Err(anyhow!("devices contain a cycle through '{}'", current))
             }
+            None => {}
         }
 
-        if queue.len() > 100000000 {
-            return None;
+        visits.insert(current, Visit::InProgress);
+
+        let mut count: u64 = 0;
+        for &next in self.routes.get(current).into_iter().flatten() {
+            count = count
+                .checked_add(self.count_paths_from(next, dst, visits)?)
+                .ok_or_else(|| anyhow!("too many paths from '{}' to '{}'", current, dst))?;
         }
+
+        visits.insert(current, Visit::Done(count));
+        Ok(count)
     }
 
-    Some(sum)
+    // Counts the paths from `src` to `dst` which pass through both `via1` and `via2`. In a DAG,
+    // every such path visits them in one of the two orders, and the path can be split into
+    // segments at them.
+    fn count_paths_via(
+        &self,
+        src: &'a str,
+        via1: &'a str,
+        via2: &'a str,
+        dst: &'a str,
+    ) -> anyhow::Result<u64> {
+        let overflow = || anyhow!("too many paths from '{}' to '{}'", src, dst);
+        let through = |first, second| -> anyhow::Result<u64> {
+            let to_first = self.count_paths(src, first)?;
+            let between = self.count_paths(first, second)?;
+            let from_second = self.count_paths(second, dst)?;
+
+            to_first
+                .checked_mul(between)
+                .and_then(|count| count.checked_mul(from_second))
+                .ok_or_else(overflow)
+        };
+
+        through(via1, via2)?
+            .checked_add(through(via2, via1)?)
+            .ok_or_else(overflow)
+    }
 }
 
-pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u32>
+pub fn part1<'a, I, S>(lines: I) -> anyhow::Result<u64>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut routes = HashMap::new();
-
-    for line in lines.into_iter().map(|l| l.as_ref()) {
-        let (from, to) = line.split_once(":").expect("line to contain ':'");
-        let to = to.split(" ").skip(1).collect::<Vec<_>>();
+    let devices = Devices::parse(lines);
 
-        routes.insert(from, to);
-    }
-
-    Ok(find_paths(&routes, "you", "out"))
+    devices.count_paths("you", "out")
 }
 
-pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u32>
+pub fn part2<'a, I, S>(lines: I) -> anyhow::Result<u64>
 where
     I: IntoIterator<Item = &'a S>,
     S: AsRef<str> + 'a,
 {
-    let mut routes = HashMap::new();
-    let mut routes_backwards: HashMap<&str, Vec<&str>> = HashMap::new();
-
-    for line in lines.into_iter().map(|l| l.as_ref()) {
-        let (from, to) = line.split_once(":").expect("line to contain ':'");
-        let to = to.split(" ").skip(1).collect::<Vec<_>>();
-
-        for &t in to.iter() {
-            routes_backwards.entry(t).or_default().push(from);
-        }
+    let devices = Devices::parse(lines);
 
-        routes.insert(from, to);
-    }
-
-    // let fft_from_svr = find_paths_short_circuit(&routes_backwards, "fft", "svr");
-    // let svr_to_fft = find_paths_short_circuit(&routes, "svr", "fft");
-    // println!("fft_from_svr: {fft_from_svr:?}, svr_to_fft: {svr_to_fft:?}");
-    //
-    // let dac_from_svr = find_paths_short_circuit(&routes_backwards, "dac", "svr");
-    // let svr_to_dac = find_paths_short_circuit(&routes, "svr", "dac");
-    // println!("dac_from_svr: {dac_from_svr:?}, svr_to_dac: {svr_to_dac:?}");
-
-    let dac_from_fft = find_paths_short_circuit(&routes_backwards, "dac", "fft");
-    let fft_to_dac = find_paths_short_circuit(&routes, "fft", "dac");
-    println!("dac_from_fft: {dac_from_fft:?}, fft_to_dac: {fft_to_dac:?}");
-
-    // let fft_from_dac = find_paths_short_circuit(&routes_backwards, "fft", "dac");
-    // let dac_to_fft = find_paths_short_circuit(&routes, "dac", "fft");
-    // println!("fft_from_dac: {fft_from_dac:?}, dac_to_fft: {dac_to_fft:?}");
-    //
-    // let out_from_dac = find_paths_short_circuit(&routes_backwards, "out", "dac");
-    // let dac_to_out = find_paths_short_circuit(&routes, "dac", "out");
-    // println!("out_from_dac: {out_from_dac:?}, dac_to_out: {dac_to_out:?}");
-    //
-    // let out_from_fft = find_paths_short_circuit(&routes_backwards, "out", "fft");
-    // let fft_to_out = find_paths_short_circuit(&routes, "fft", "out");
-    // println!("out_from_fft: {out_from_fft:?}, fft_to_out: {fft_to_out:?}");
-
-    Ok(0)
+    devices.count_paths_via("svr", "dac", "fft", "out")
 }
 
 #[cfg(test)]
@@ -159,4 +163,60 @@ mod tests {
 
         assert_eq!(result, 2);
     }
+
+    #[test]
+    fn count_paths_cycle_test() {
+        let devices = Devices::parse(&["you: aaa", "aaa: bbb out", "bbb: aaa"]);
+
+        assert!(devices.count_paths("you", "out").is_err());
+    }
+
+    // A chain of `n` diamonds from `from` to `to`, each of which doubles the number of paths
+    fn diamonds(name: &str, n: usize, from: &str, to: &str) -> Vec<String> {
+        let node = |i: usize| match i {
+            0 => from.to_string(),
+            i if i == n => to.to_string(),
+            i => format!("{}{}", name, i),
+        };
+
+        (0..n)
+            .flat_map(|i| {
+                let (l, r) = (format!("{}{}l", name, i), format!("{}{}r", name, i));
+                [
+                    format!("{}: {} {}", node(i), l, r),
+                    format!("{}: {}", l, node(i + 1)),
+                    format!("{}: {}", r, node(i + 1)),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn count_paths_overflow_test() {
+        let lines = diamonds("a", 63, "you", "out");
+        assert_eq!(
+            Devices::parse(&lines).count_paths("you", "out").unwrap(),
+            1 << 63
+        );
+
+        let lines = diamonds("a", 64, "you", "out");
+        assert!(Devices::parse(&lines).count_paths("you", "out").is_err());
+
+        // Every segment fits, but not all of them together
+        let lines = [
+            diamonds("a", 32, "svr", "dac"),
+            diamonds("b", 16, "dac", "fft"),
+            diamonds("c", 15, "fft", "out"),
+        ]
+        .concat();
+        assert_eq!(part2(&lines).unwrap(), 1 << 63);
+
+        let lines = [
+            diamonds("a", 32, "svr", "dac"),
+            diamonds("b", 16, "dac", "fft"),
+            diamonds("c", 16, "fft", "out"),
+        ]
+        .concat();
+        assert!(part2(&lines).is_err());
+    }
 }